@@ -1,52 +1,286 @@
 use crate::{Vector, Triangle};
+use crate::vector::Environment;
 use image::{ImageBuffer, Rgba, RgbaImage};
 use rayon::prelude::*;
-use std::sync::{Arc, Mutex};
-
-pub fn draw_concur(vectors: &Vec<Vector>, width: u32, height: u32, num_workers: usize) -> Vec<RgbaImage> {
-    let chunk_size = (vectors.len() + num_workers - 1) / num_workers;
-    let vectors_chunks: Vec<_> = vectors.chunks(chunk_size).map(|chunk| chunk.to_vec()).collect();
-    let output_images: Arc<Mutex<Vec<Option<RgbaImage>>>> = Arc::new(Mutex::new(vec![None; vectors.len()]));
-    vectors_chunks.par_iter().enumerate().for_each(|(chunk_index, chunk)| {
-        let images = process_chunk(chunk.to_vec(), width, height);
-        let mut output = output_images.lock().unwrap();
-        for (i, image) in images.into_iter().enumerate() {
-            output[chunk_index * chunk_size + i] = Some(image);
-        }
-    });
-    Arc::try_unwrap(output_images).unwrap().into_inner().unwrap().into_iter().map(|img| img.unwrap()).collect()
+use std::sync::OnceLock;
+
+// rows per tile in the tiled mode
+const TILE_HEIGHT: usize = 32;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum RenderMode {
+    // per pixel get_pixel / put_pixel, kept as the baseline the faster modes must match exactly
+    Reference,
+    // spans blended straight into the image rows, see `Blender`
+    Scanline,
+    // scanline rendering with the rows split in tiles drawn in parallel, for large images
+    Tiled,
+}
+
+// how a triangle is painted over the canvas. every mode composites with the triangle alpha,
+// linear light is source-over done on premultiplied linear values instead of sRGB ones, so a
+// translucent canvas does not darken the colors it is mixed with
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum BlendMode {
+    SourceOver,
+    Additive,
+    Multiply,
+    Screen,
+    LinearLight,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 5] = [
+        BlendMode::SourceOver,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::LinearLight,
+    ];
 }
 
-fn process_chunk(vectors_chunk: Vec<Vector>, width: u32, height: u32) -> Vec<RgbaImage> {
-    vectors_chunk.iter().map(|vector| draw_vector(vector, width, height)).collect()
+// canvas at the evaluation resolution with the first `triangles` triangles of every vector of the
+// pool already drawn, set by optimisers that only change the triangles after them
+pub struct BaseCanvas {
+    pub image: RgbaImage,
+    pub triangles: usize,
 }
 
-pub fn draw_vector(vector: &Vector, width: u32, height: u32) -> RgbaImage {
+// renders each vector into a canvas reused by the rayon job that owns it and evaluates it
+// straight away, so no image is kept around after its measure is computed
+pub fn evaluate_concur(env: &Environment, measure: impl Fn(&RgbaImage) -> f64 + Sync) -> Vec<f64> {
+    let (width, height, mode) = (env.eval_width, env.eval_height, env.render_mode);
+    let scale = (width as f32 / env.target_width as f32, height as f32 / env.target_height as f32);
+    let base = env.base_canvas.as_ref().filter(|base| base.image.dimensions() == (width, height));
+    env.pool
+        .par_iter()
+        .map_init(
+            || RgbaImage::new(width, height),
+            |image, vector| {
+                match base {
+                    Some(base) if vector.triangles.len() >= base.triangles => {
+                        image.copy_from_slice(&base.image);
+                        draw_triangles(image, &vector.triangles[base.triangles..], scale, mode);
+                    }
+                    _ => {
+                        let background = env.background(vector, env.background_params.composite_measures);
+                        draw_vector_into(image, vector, background, scale, mode);
+                    }
+                }
+                measure(image)
+            },
+        )
+        .collect()
+}
+
+pub fn draw_vector(vector: &Vector, width: u32, height: u32, background: Rgba<u8>, mode: RenderMode) -> RgbaImage {
     let mut image = ImageBuffer::new(width, height);
-    for triangle in &vector.triangles {
-        draw_triangle(&mut image, triangle);
-    }
+    draw_vector_into(&mut image, vector, background, (1.0, 1.0), mode);
     image
 }
 
-fn blend_pixel(dst: &Rgba<u8>, src: &Rgba<u8>) -> Rgba<u8> {
+// fills `image` with the background and draws the vector over it, with the vertices multiplied
+// by `scale` when the canvas is smaller than the target the vector was evolved for
+pub fn draw_vector_into(image: &mut RgbaImage, vector: &Vector, background: Rgba<u8>, scale: (f32, f32), mode: RenderMode) {
+    for pixel in image.pixels_mut() {
+        *pixel = background;
+    }
+    draw_triangles(image, &vector.triangles, scale, mode);
+}
+
+// draws `triangles` over what `image` already holds
+pub fn draw_triangles(image: &mut RgbaImage, triangles: &[Triangle], scale: (f32, f32), mode: RenderMode) {
+    let width = image.width() as usize;
+    if mode == RenderMode::Reference {
+        for triangle in triangles {
+            draw_triangle(image, triangle, scale);
+        }
+        return;
+    }
+    // the blenders are shared by all tiles
+    let blenders: Vec<Blender> = triangles.iter().map(|triangle| Blender::new(&triangle.color, triangle.blend)).collect();
+    match mode {
+        RenderMode::Tiled => {
+            image
+                .par_chunks_mut(width * 4 * TILE_HEIGHT)
+                .enumerate()
+                .for_each(|(tile, rows)| draw_rows(rows, width, tile * TILE_HEIGHT, triangles, &blenders, scale));
+        }
+        _ => draw_rows(image, width, 0, triangles, &blenders, scale),
+    }
+}
+
+// draws the triangles over `rows`, which holds the image rows starting at `first_row`
+fn draw_rows(rows: &mut [u8], width: usize, first_row: usize, triangles: &[Triangle], blenders: &[Blender], scale: (f32, f32)) {
+    if width == 0 {
+        return;
+    }
+    let row_range = first_row as i32..(first_row + rows.len() / (width * 4)) as i32;
+    for (triangle, blender) in triangles.iter().zip(blenders) {
+        for_each_scanline(triangle, scale, width as u32, row_range.clone(), |y, start, end| {
+            let offset = (y as usize - first_row) * width * 4;
+            blender.blend_span(&mut rows[offset + start as usize * 4..offset + end as usize * 4 + 4]);
+        });
+    }
+}
+
+// `blend_pixel` with the source fixed, built once per triangle so a span only does the work that
+// depends on the destination. each channel of source-over, additive, multiply and screen only
+// depends on its own destination value, so it is a table filled with the reference's f32 and
+// integer formulas; linear light keeps its weighted source values and only mixes in the
+// destination per pixel. every pixel is the one the reference renderer gives
+struct Blender {
+    mode: BlendMode,
+    // the blended value of every destination value, per channel; only the alpha one for linear light
+    channels: [[u8; 256]; 4],
+    // linear light: the weighted linear source values, the source weight and 255 - alpha, which
+    // weighs the destination alpha
+    linear_terms: [u64; 3],
+    src_weight: u64,
+    inv_src_alpha: u64,
+    tables: &'static (Vec<u16>, Vec<u8>),
+}
+
+impl Blender {
+    fn new(color: &Rgba<u8>, mode: BlendMode) -> Self {
+        let alpha = color[3] as f32 / 255.0;
+        let (src_alpha, inv_src_alpha) = (color[3] as u32, 255 - color[3] as u32);
+        let src_terms = [color[0] as f32 * alpha, color[1] as f32 * alpha, color[2] as f32 * alpha, color[3] as f32];
+        let channels = std::array::from_fn(|c| {
+            // the source factor of additive, multiply and screen
+            let s = color[c] as u32;
+            let factor = match mode {
+                BlendMode::Additive => s * src_alpha,
+                BlendMode::Multiply => 255 * inv_src_alpha + s * src_alpha,
+                BlendMode::Screen => 255 * inv_src_alpha + (255 - s) * src_alpha,
+                _ => 0,
+            };
+            std::array::from_fn(|d| match mode {
+                _ if c == 3 => (src_terms[3] + d as f32 * (1.0 - alpha)) as u8,
+                BlendMode::SourceOver => (src_terms[c] + d as f32 * (1.0 - alpha)) as u8,
+                BlendMode::Additive => ((d as u32 * 255 + factor) / 255).min(255) as u8,
+                BlendMode::Multiply => (d as u32 * factor / 65025) as u8,
+                BlendMode::Screen => (255 - (255 - d as u32) * factor / 65025) as u8,
+                // its colors are blended per pixel
+                BlendMode::LinearLight => 0,
+            })
+        });
+        let tables = linear_tables();
+        let src_weight = src_alpha as u64 * 255;
+        Self {
+            mode,
+            channels,
+            linear_terms: std::array::from_fn(|c| tables.0[color[c] as usize] as u64 * src_weight),
+            src_weight,
+            inv_src_alpha: inv_src_alpha as u64,
+            tables,
+        }
+    }
+
+    fn blend_span(&self, span: &mut [u8]) {
+        match self.mode {
+            // depends on the destination alpha as well
+            BlendMode::LinearLight => {
+                let (to_linear, to_srgb) = self.tables;
+                for pixel in span.chunks_exact_mut(4) {
+                    let dst_weight = pixel[3] as u64 * self.inv_src_alpha;
+                    let weight = self.src_weight + dst_weight;
+                    for (value, term) in pixel[..3].iter_mut().zip(self.linear_terms) {
+                        let linear = (term + to_linear[*value as usize] as u64 * dst_weight).checked_div(weight);
+                        *value = linear.map_or(0, |linear| to_srgb[linear as usize]);
+                    }
+                    pixel[3] = self.channels[3][pixel[3] as usize];
+                }
+            }
+            _ => {
+                for pixel in span.chunks_exact_mut(4) {
+                    for (value, channel) in pixel.iter_mut().zip(&self.channels) {
+                        *value = channel[*value as usize];
+                    }
+                }
+            }
+        }
+    }
+}
+
+// sRGB to 16 bit linear and 16 bit linear back to sRGB, built on first use
+fn linear_tables() -> &'static (Vec<u16>, Vec<u8>) {
+    static TABLES: OnceLock<(Vec<u16>, Vec<u8>)> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let to_linear = (0..256)
+            .map(|v| {
+                let v = v as f64 / 255.0;
+                let linear = if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) };
+                (linear * 65535.0).round() as u16
+            })
+            .collect();
+        let to_srgb = (0..65536)
+            .map(|v| {
+                let v = v as f64 / 65535.0;
+                let srgb = if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+                (srgb * 255.0).round() as u8
+            })
+            .collect();
+        (to_linear, to_srgb)
+    })
+}
+
+fn blend_pixel(dst: &Rgba<u8>, src: &Rgba<u8>, mode: BlendMode) -> Rgba<u8> {
     let alpha = src[3] as f32 / 255.0;
     let inv_alpha = 1.0 - alpha;
+    let (src_alpha, inv_src_alpha) = (src[3] as u32, 255 - src[3] as u32);
 
-    let r = (src[0] as f32 * alpha + dst[0] as f32 * inv_alpha) as u8;
-    let g = (src[1] as f32 * alpha + dst[1] as f32 * inv_alpha) as u8;
-    let b = (src[2] as f32 * alpha + dst[2] as f32 * inv_alpha) as u8;
+    let blend = |c: usize| {
+        let (s, d) = (src[c] as u32, dst[c] as u32);
+        match mode {
+            BlendMode::SourceOver => (src[c] as f32 * alpha + dst[c] as f32 * inv_alpha) as u8 as u32,
+            BlendMode::Additive => ((d * 255 + s * src_alpha) / 255).min(255),
+            // d + alpha * (s * d - d), all over 255 * 255
+            BlendMode::Multiply => d * (255 * inv_src_alpha + s * src_alpha) / 65025,
+            // the complement of multiply on the complements
+            BlendMode::Screen => 255 - (255 - d) * (255 * inv_src_alpha + (255 - s) * src_alpha) / 65025,
+            // source-over of the premultiplied values, divided by the resulting alpha
+            BlendMode::LinearLight => {
+                let (to_linear, to_srgb) = linear_tables();
+                let (src_weight, dst_weight) = (src_alpha as u64 * 255, dst[3] as u64 * inv_src_alpha as u64);
+                let weight = src_weight + dst_weight;
+                if weight == 0 {
+                    return 0;
+                }
+                let linear = (to_linear[s as usize] as u64 * src_weight + to_linear[d as usize] as u64 * dst_weight) / weight;
+                to_srgb[linear as usize] as u32
+            }
+        }
+    };
     let a = (src[3] as f32 + dst[3] as f32 * inv_alpha) as u8;
 
-    Rgba([r, g, b, a])
+    Rgba([blend(0) as u8, blend(1) as u8, blend(2) as u8, a])
 }
 
-fn draw_triangle(image: &mut RgbaImage, triangle: &Triangle) {
+fn draw_triangle(image: &mut RgbaImage, triangle: &Triangle, scale: (f32, f32)) {
     let color = triangle.color;
+    let (width, height) = (image.width(), image.height());
+    for_each_scanline(triangle, scale, width, 0..height as i32, |y, start, end| {
+        for x in start..=end {
+            let dst_pixel = image.get_pixel(x, y as u32);
+            let blended_pixel = blend_pixel(dst_pixel, &color, triangle.blend);
+            image.put_pixel(x, y as u32, blended_pixel);
+        }
+    });
+}
 
-    let (mut x1, mut y1) = (triangle.vertex1.x as i32, triangle.vertex1.y as i32);
-    let (mut x2, mut y2) = (triangle.vertex2.x as i32, triangle.vertex2.y as i32);
-    let (mut x3, mut y3) = (triangle.vertex3.x as i32, triangle.vertex3.y as i32);
+// calls `draw_scanline(y, start, end)` with the inclusive pixel span of every row of the
+// triangle inside `rows`. the middle row is visited by both halves, as it always has been
+fn for_each_scanline(triangle: &Triangle, scale: (f32, f32), width: u32, rows: std::ops::Range<i32>, mut draw_scanline: impl FnMut(i32, u32, u32)) {
+    let vertex = |x: u32, y: u32| ((x as f32 * scale.0) as i32, (y as f32 * scale.1) as i32);
+    let (mut x1, mut y1) = vertex(triangle.vertex1.x, triangle.vertex1.y);
+    let (mut x2, mut y2) = vertex(triangle.vertex2.x, triangle.vertex2.y);
+    let (mut x3, mut y3) = vertex(triangle.vertex3.x, triangle.vertex3.y);
 
     if y1 > y2 {
         std::mem::swap(&mut y1, &mut y2);
@@ -60,6 +294,10 @@ fn draw_triangle(image: &mut RgbaImage, triangle: &Triangle) {
         std::mem::swap(&mut y2, &mut y3);
         std::mem::swap(&mut x2, &mut x3);
     }
+    // outside the rows, as for most triangles of a tile
+    if y3 < rows.start || y1 >= rows.end {
+        return;
+    }
 
     let interpolate = |y, y1, y2, x1, x2| -> f32 {
         if y1 == y2 {
@@ -68,35 +306,113 @@ fn draw_triangle(image: &mut RgbaImage, triangle: &Triangle) {
         x1 as f32 + (x2 as f32 - x1 as f32) * (y as f32 - y1 as f32) / (y2 as f32 - y1 as f32)
     };
 
-    let mut draw_scanline = |y: i32, x_start: f32, x_end: f32| {
-        if y >= 0 && y < image.height() as i32 {
-            let start = x_start.max(0.0) as u32;
-            let end = x_end.min(image.width() as f32 - 1.0) as u32;
-            for x in start..=end {
-                let dst_pixel = image.get_pixel(x, y as u32);
-                let blended_pixel = blend_pixel(dst_pixel, &color);
-                image.put_pixel(x, y as u32, blended_pixel);
-            }
+    let mut scanline = |y: i32, x_start: f32, x_end: f32| {
+        let (x_start, x_end) = if x_start <= x_end { (x_start, x_end) } else { (x_end, x_start) };
+        let start = x_start.max(0.0) as u32;
+        let end = x_end.min(width as f32 - 1.0) as u32;
+        if start <= end {
+            draw_scanline(y, start, end);
         }
     };
 
-    for y in y1..=y2 {
-        let x_start = interpolate(y, y1, y3, x1, x3);
-        let x_end = interpolate(y, y1, y2, x1, x2);
-        if x_start <= x_end {
-            draw_scanline(y, x_start, x_end);
-        } else {
-            draw_scanline(y, x_end, x_start);
+    for y in y1.max(rows.start)..=y2.min(rows.end - 1) {
+        scanline(y, interpolate(y, y1, y3, x1, x3), interpolate(y, y1, y2, x1, x2));
+    }
+
+    for y in y2.max(rows.start)..=y3.min(rows.end - 1) {
+        scanline(y, interpolate(y, y1, y3, x1, x3), interpolate(y, y2, y3, x2, x3));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vertex;
+    use rand::Rng;
+
+    fn random_vector(width: u32, height: u32, blend: BlendMode) -> Vector {
+        let mut rng = rand::thread_rng();
+        // vertices may lie outside the canvas so the clipping is covered as well
+        let vertex = |rng: &mut rand::rngs::ThreadRng| Vertex { x: rng.gen_range(0..width + 20), y: rng.gen_range(0..height + 20) };
+        let triangles = (0..60)
+            .map(|_| Triangle {
+                vertex1: vertex(&mut rng),
+                vertex2: vertex(&mut rng),
+                vertex3: vertex(&mut rng),
+                color: Rgba(rng.gen()),
+                blend,
+            })
+            .collect();
+        Vector {
+            triangles,
+            background: [0; 3],
+            fitness: Vec::new(),
+            scores: Vec::new(),
+            rank: 0,
+            crowding_distance: 0.0,
         }
     }
 
-    for y in y2..=y3 {
-        let x_start = interpolate(y, y1, y3, x1, x3);
-        let x_end = interpolate(y, y2, y3, x2, x3);
-        if x_start <= x_end {
-            draw_scanline(y, x_start, x_end);
-        } else {
-            draw_scanline(y, x_end, x_start);
+    #[test]
+    fn render_modes_match_reference() {
+        for blend in BlendMode::ALL {
+            for (width, height) in [(1, 1), (53, 31), (64, 70), (37, 100)] {
+                for background in [Rgba([0, 0, 0, 0]), Rgba([200, 30, 90, 255])] {
+                    for scale in [(1.0, 1.0), (0.5, 0.37)] {
+                        let vector = random_vector(width, height, blend);
+                        let render = |mode| {
+                            let mut image = RgbaImage::new(width, height);
+                            draw_vector_into(&mut image, &vector, background, scale, mode);
+                            image
+                        };
+                        let reference = render(RenderMode::Reference);
+                        assert!(reference == render(RenderMode::Scanline), "{:?} {}x{}", blend, width, height);
+                        assert!(reference == render(RenderMode::Tiled), "{:?} {}x{}", blend, width, height);
+                    }
+                }
+            }
         }
     }
+
+    #[test]
+    fn source_over_is_the_original_f32_blend() {
+        for (src, alpha, dst) in [(0, 65, 51), (255, 130, 0), (17, 50, 240)] {
+            let blended = blend_pixel(&Rgba([dst, dst, dst, dst]), &Rgba([src, src, src, alpha]), BlendMode::SourceOver);
+            let a = alpha as f32 / 255.0;
+            let expected = (src as f32 * a + dst as f32 * (1.0 - a)) as u8;
+            assert_eq!(blended[0], expected);
+            assert_eq!(blended[3], (alpha as f32 + dst as f32 * (1.0 - a)) as u8);
+        }
+    }
+
+    // the scanline blender must give the result of `blend_pixel` for every destination value
+    // and source alpha. every source value as well for the f32 source-over, a sample of them for
+    // the integer modes
+    #[test]
+    fn blender_matches_blend_pixel_exhaustively() {
+        for mode in BlendMode::ALL {
+            let step = if mode == BlendMode::SourceOver { 1 } else { 15 };
+            for alpha in 0..=255u8 {
+                for src in (0..=255u8).step_by(step) {
+                    let color = Rgba([src, 255 - src, src / 2, alpha]);
+                    let blender = Blender::new(&color, mode);
+                    let mut span: Vec<u8> = (0..=255u8).flat_map(|v| [v, 255 - v, v / 3, v]).collect();
+                    let expected: Vec<u8> = span.chunks_exact(4).flat_map(|dst| blend_pixel(&Rgba([dst[0], dst[1], dst[2], dst[3]]), &color, mode).0).collect();
+                    blender.blend_span(&mut span);
+                    assert_eq!(span, expected, "{:?} {:?}", mode, color);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn linear_light_is_premultiplied() {
+        // a transparent canvas leaves the source color as it is, whatever color it holds
+        let blended = blend_pixel(&Rgba([0, 0, 0, 0]), &Rgba([200, 100, 50, 80]), BlendMode::LinearLight);
+        assert_eq!(blended, Rgba([200, 100, 50, 80]));
+        // a canvas as opaque as the source weighs more than it, as it covers more of the pixel
+        let blended = blend_pixel(&Rgba([0, 0, 0, 255]), &Rgba([255, 255, 255, 128]), BlendMode::LinearLight);
+        let half_covered = blend_pixel(&Rgba([0, 0, 0, 128]), &Rgba([255, 255, 255, 128]), BlendMode::LinearLight);
+        assert!(blended[0] < half_covered[0]);
+    }
 }
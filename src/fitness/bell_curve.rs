@@ -0,0 +1,111 @@
+use image::RgbaImage;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use crate::draw::evaluate_concur;
+use crate::vector::Environment;
+
+#[derive(Clone)]
+pub struct BellCurveParams {
+    pub detection_threshold: f64,
+    pub bins_per_std_dev: f64,
+    pub fit_distribution: bool,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Default for BellCurveParams {
+    fn default() -> Self {
+        Self {
+            detection_threshold: 2.0,
+            bins_per_std_dev: 100.0,
+            fit_distribution: true,
+            mean: 2.0,
+            std_dev: 1.0,
+        }
+    }
+}
+
+pub fn calculate_fitness(env: &mut Environment, idx: usize) {
+    let fitness_values: Vec<f64> = evaluate_concur(env, |image| calculate_bell_curve(&env.fitness_params.bell_curve, image));
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+// Ross and Ralph deviation from normality: the response weighted distribution of the color
+// gradient responses is compared against a normal curve with 1000 * KL(observed || normal)
+fn calculate_bell_curve(params: &BellCurveParams, image: &RgbaImage) -> f64 {
+    let responses = gradient_responses(image, params.detection_threshold);
+    let total_response: f64 = responses.iter().sum();
+    if responses.len() < 2 || total_response == 0.0 {
+        return 1000.0;
+    }
+
+    let (mean, std_dev) = if params.fit_distribution {
+        let mean = responses.iter().map(|r| r * r).sum::<f64>() / total_response;
+        let variance = responses.iter().map(|r| r * (r - mean).powi(2)).sum::<f64>() / total_response;
+        (mean, variance.sqrt())
+    } else {
+        (params.mean, params.std_dev)
+    };
+
+    let normal = match Normal::new(mean, std_dev) {
+        Ok(normal) if std_dev > 0.0 => normal,
+        _ => return 1000.0,
+    };
+
+    let min_response = responses.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max_response = responses.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let bin_width = std_dev / params.bins_per_std_dev.max(1.0);
+    let num_bins = (((max_response - min_response) / bin_width).floor() as usize + 1).min(100_000);
+
+    let mut histogram = vec![0.0; num_bins];
+    for response in &responses {
+        let bin = (((response - min_response) / bin_width) as usize).min(num_bins - 1);
+        histogram[bin] += response;
+    }
+
+    let mut deviation = 0.0;
+    for (bin, weight) in histogram.iter().enumerate() {
+        if *weight == 0.0 {
+            continue;
+        }
+        let observed = weight / total_response;
+        let lower = min_response + bin as f64 * bin_width;
+        let expected = (normal.cdf(lower + bin_width) - normal.cdf(lower)).max(f64::EPSILON);
+        deviation += observed * (observed / expected).ln();
+    }
+
+    1000.0 * deviation
+}
+
+// stimulus of each pixel from its diagonal color differences, scaled by 0.1% of the diagonal,
+// turned into log responses above the detection threshold
+fn gradient_responses(image: &RgbaImage, detection_threshold: f64) -> Vec<f64> {
+    let (width, height) = (image.width(), image.height());
+    let distance = 0.001 * ((width * width + height * height) as f64).sqrt();
+    let distance_squared = distance * distance;
+    let mut responses = Vec::new();
+
+    for y in 0..height.saturating_sub(1) {
+        for x in 0..width.saturating_sub(1) {
+            let p = image.get_pixel(x, y).0;
+            let p_diag = image.get_pixel(x + 1, y + 1).0;
+            let p_right = image.get_pixel(x + 1, y).0;
+            let p_down = image.get_pixel(x, y + 1).0;
+
+            let mut stimulus_squared = 0.0;
+            for c in 0..3 {
+                let d1 = p[c] as f64 - p_diag[c] as f64;
+                let d2 = p_right[c] as f64 - p_down[c] as f64;
+                stimulus_squared += (d1 * d1 + d2 * d2) / distance_squared;
+            }
+
+            let stimulus = stimulus_squared.sqrt();
+            if stimulus > detection_threshold {
+                responses.push((stimulus / detection_threshold).ln());
+            }
+        }
+    }
+    responses
+}
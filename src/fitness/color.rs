@@ -0,0 +1,224 @@
+use image::RgbaImage;
+
+use crate::draw::evaluate_concur;
+use crate::fitness::delta_e::{ciede2000, rgba_to_lab};
+use crate::vector::Environment;
+
+pub const DEFAULT_PALETTE: &str = "#264653, #2a9d8f, #e9c46a, #f4a261, #e76f51";
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum HarmonicTemplate {
+    Any,
+    I,
+    V,
+    L,
+    II,
+    T,
+    Y,
+    X,
+}
+
+impl HarmonicTemplate {
+    pub const ALL: [HarmonicTemplate; 8] = [
+        HarmonicTemplate::Any,
+        HarmonicTemplate::I,
+        HarmonicTemplate::V,
+        HarmonicTemplate::L,
+        HarmonicTemplate::II,
+        HarmonicTemplate::T,
+        HarmonicTemplate::Y,
+        HarmonicTemplate::X,
+    ];
+
+    // (sector center offset, sector width) in degrees, as defined by Cohen-Or et al.
+    fn sectors(&self) -> &'static [(f64, f64)] {
+        match self {
+            HarmonicTemplate::Any => &[],
+            HarmonicTemplate::I => &[(0.0, 18.0)],
+            HarmonicTemplate::V => &[(0.0, 93.6)],
+            HarmonicTemplate::L => &[(0.0, 18.0), (90.0, 79.2)],
+            HarmonicTemplate::II => &[(0.0, 18.0), (180.0, 18.0)],
+            HarmonicTemplate::T => &[(0.0, 180.0)],
+            HarmonicTemplate::Y => &[(0.0, 93.6), (180.0, 18.0)],
+            HarmonicTemplate::X => &[(0.0, 93.6), (180.0, 93.6)],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ColorParams {
+    pub harmonic_template: HarmonicTemplate,
+    pub palette: Vec<[u8; 3]>,
+}
+
+impl Default for ColorParams {
+    fn default() -> Self {
+        Self {
+            harmonic_template: HarmonicTemplate::Any,
+            palette: parse_palette(DEFAULT_PALETTE),
+        }
+    }
+}
+
+// parses a comma or whitespace separated list of hex colors, invalid entries are skipped
+pub fn parse_palette(text: &str) -> Vec<[u8; 3]> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|entry| {
+            let hex = entry.trim().trim_start_matches('#');
+            if hex.len() != 6 {
+                return None;
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            Some([channel(0)?, channel(2)?, channel(4)?])
+        })
+        .collect()
+}
+
+pub fn calculate_colorfulness(env: &mut Environment, idx: usize) {
+    let fitness_values: Vec<f64> = evaluate_concur(env, colorfulness);
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+pub fn calculate_harmony(env: &mut Environment, idx: usize) {
+    let template = env.fitness_params.color.harmonic_template;
+    let fitness_values: Vec<f64> = evaluate_concur(env, |image| harmony(image, template));
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+pub fn calculate_palette_distance(env: &mut Environment, idx: usize) {
+    let palette_lab: Vec<[f64; 3]> = env
+        .fitness_params
+        .color
+        .palette
+        .iter()
+        .map(|&[r, g, b]| rgba_to_lab([r, g, b, 255], true))
+        .collect();
+    let fitness_values: Vec<f64> = evaluate_concur(env, |image| palette_distance(image, &palette_lab));
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+// Hasler and Süsstrunk colorfulness on the rg / yb opponent channels
+fn colorfulness(image: &RgbaImage) -> f64 {
+    let n = (image.width() * image.height()) as f64;
+    let (mut sum_rg, mut sum_yb, mut sum_rg2, mut sum_yb2) = (0.0, 0.0, 0.0, 0.0);
+
+    for pixel in image.pixels() {
+        let [r, g, b, _] = pixel.0;
+        let (r, g, b) = (r as f64, g as f64, b as f64);
+        let rg = r - g;
+        let yb = 0.5 * (r + g) - b;
+        sum_rg += rg;
+        sum_yb += yb;
+        sum_rg2 += rg * rg;
+        sum_yb2 += yb * yb;
+    }
+
+    let mean_rg = sum_rg / n;
+    let mean_yb = sum_yb / n;
+    let var_rg = (sum_rg2 / n - mean_rg * mean_rg).max(0.0);
+    let var_yb = (sum_yb2 / n - mean_yb * mean_yb).max(0.0);
+
+    (var_rg + var_yb).sqrt() + 0.3 * (mean_rg * mean_rg + mean_yb * mean_yb).sqrt()
+}
+
+// saturation weighted mean arc distance (in degrees) between each hue and the closest sector
+// of the best rotation of the template; `Any` picks the template that fits best
+fn harmony(image: &RgbaImage, template: HarmonicTemplate) -> f64 {
+    let mut histogram = [0.0; 360];
+    let mut total_saturation = 0.0;
+    for pixel in image.pixels() {
+        let (hue, saturation) = hue_saturation(pixel.0);
+        histogram[(hue as usize).min(359)] += saturation;
+        total_saturation += saturation;
+    }
+
+    if total_saturation == 0.0 {
+        return 0.0;
+    }
+
+    let templates: Vec<HarmonicTemplate> = if template == HarmonicTemplate::Any {
+        HarmonicTemplate::ALL.iter().copied().filter(|t| *t != HarmonicTemplate::Any).collect()
+    } else {
+        vec![template]
+    };
+
+    let mut best = f64::INFINITY;
+    for template in templates {
+        for rotation in (0..360).step_by(2) {
+            let mut distance = 0.0;
+            for (hue, weight) in histogram.iter().enumerate() {
+                if *weight == 0.0 {
+                    continue;
+                }
+                distance += weight * distance_to_template(hue as f64 + 0.5, rotation as f64, template.sectors());
+            }
+            best = best.min(distance);
+        }
+    }
+
+    best / total_saturation
+}
+
+fn distance_to_template(hue: f64, rotation: f64, sectors: &[(f64, f64)]) -> f64 {
+    sectors
+        .iter()
+        .map(|(offset, width)| {
+            let center = rotation + offset;
+            let arc = (hue - center).rem_euclid(360.0);
+            let arc = arc.min(360.0 - arc);
+            (arc - width / 2.0).max(0.0)
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn hue_saturation(rgba: [u8; 4]) -> (f64, f64) {
+    let r = rgba[0] as f64 / 255.0;
+    let g = rgba[1] as f64 / 255.0;
+    let b = rgba[2] as f64 / 255.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    if delta == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    (hue, delta / max)
+}
+
+// mean CIEDE2000 distance from each pixel to its closest palette color
+fn palette_distance(image: &RgbaImage, palette_lab: &[[f64; 3]]) -> f64 {
+    if palette_lab.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = image
+        .pixels()
+        .map(|pixel| {
+            let lab = rgba_to_lab(pixel.0, true);
+            palette_lab
+                .iter()
+                .map(|color| ciede2000(&lab, color))
+                .fold(f64::INFINITY, f64::min)
+        })
+        .sum();
+
+    total / (image.width() * image.height()) as f64
+}
@@ -0,0 +1,9 @@
+use crate::vector::Environment;
+
+// number of triangles in the genome, to be minimised next to the image measures when the
+// genomes have a variable length
+pub fn calculate_fitness(env: &mut Environment, idx: usize) {
+    for vector in env.pool.iter_mut() {
+        vector.fitness[idx] = vector.triangles.len() as f64;
+    }
+}
@@ -0,0 +1,134 @@
+use image::RgbaImage;
+
+use crate::draw::evaluate_concur;
+use crate::vector::Environment;
+
+pub fn calculate_horizontal_symmetry(env: &mut Environment, idx: usize) {
+    calculate_fitness(env, idx, horizontal_symmetry);
+}
+
+pub fn calculate_vertical_symmetry(env: &mut Environment, idx: usize) {
+    calculate_fitness(env, idx, vertical_symmetry);
+}
+
+pub fn calculate_rotational_symmetry(env: &mut Environment, idx: usize) {
+    calculate_fitness(env, idx, rotational_symmetry);
+}
+
+pub fn calculate_visual_balance(env: &mut Environment, idx: usize) {
+    calculate_fitness(env, idx, visual_balance);
+}
+
+pub fn calculate_rule_of_thirds(env: &mut Environment, idx: usize) {
+    calculate_fitness(env, idx, rule_of_thirds);
+}
+
+fn calculate_fitness(env: &mut Environment, idx: usize, measure: fn(&[f64], usize, usize) -> f64) {
+    let fitness_values: Vec<f64> = evaluate_concur(
+        env,
+        |image| {
+            let luminances = compute_luminances(image);
+            measure(&luminances, image.width() as usize, image.height() as usize)
+        }
+    );
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+fn compute_luminances(image: &RgbaImage) -> Vec<f64> {
+    image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, _] = pixel.0;
+            (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64) / 255.0
+        })
+        .collect()
+}
+
+// mean luminance difference between each pixel and its counterpart, 0 means perfectly symmetric
+fn mirrored_difference(luminances: &[f64], width: usize, height: usize, mirror: impl Fn(usize, usize) -> (usize, usize)) -> f64 {
+    let mut difference = 0.0;
+    for y in 0..height {
+        for x in 0..width {
+            let (mx, my) = mirror(x, y);
+            difference += (luminances[y * width + x] - luminances[my * width + mx]).abs();
+        }
+    }
+    difference / luminances.len() as f64
+}
+
+// left half mirrored onto the right half
+fn horizontal_symmetry(luminances: &[f64], width: usize, height: usize) -> f64 {
+    mirrored_difference(luminances, width, height, |x, y| (width - 1 - x, y))
+}
+
+// top half mirrored onto the bottom half
+fn vertical_symmetry(luminances: &[f64], width: usize, height: usize) -> f64 {
+    mirrored_difference(luminances, width, height, |x, y| (x, height - 1 - y))
+}
+
+// image rotated by 180 degrees around its center
+fn rotational_symmetry(luminances: &[f64], width: usize, height: usize) -> f64 {
+    mirrored_difference(luminances, width, height, |x, y| (width - 1 - x, height - 1 - y))
+}
+
+// distance between the luminance weighted center of mass and the image center,
+// normalised by half of the diagonal
+fn visual_balance(luminances: &[f64], width: usize, height: usize) -> f64 {
+    let mut total_weight = 0.0;
+    let mut center_x = 0.0;
+    let mut center_y = 0.0;
+    for y in 0..height {
+        for x in 0..width {
+            let weight = luminances[y * width + x];
+            total_weight += weight;
+            center_x += weight * x as f64;
+            center_y += weight * y as f64;
+        }
+    }
+
+    if total_weight == 0.0 {
+        return 0.0;
+    }
+
+    let dx = center_x / total_weight - (width - 1) as f64 / 2.0;
+    let dy = center_y / total_weight - (height - 1) as f64 / 2.0;
+    let half_diagonal = ((width * width + height * height) as f64).sqrt() / 2.0;
+    (dx * dx + dy * dy).sqrt() / half_diagonal
+}
+
+// share of the salient pixels (far from the mean luminance) that lie close to the four
+// intersections of the thirds lines, weighted by a gaussian falloff
+fn rule_of_thirds(luminances: &[f64], width: usize, height: usize) -> f64 {
+    let mean = luminances.iter().sum::<f64>() / luminances.len() as f64;
+    let sigma = 0.1 * ((width * width + height * height) as f64).sqrt();
+    let power_points = [
+        (width as f64 / 3.0, height as f64 / 3.0),
+        (2.0 * width as f64 / 3.0, height as f64 / 3.0),
+        (width as f64 / 3.0, 2.0 * height as f64 / 3.0),
+        (2.0 * width as f64 / 3.0, 2.0 * height as f64 / 3.0),
+    ];
+
+    let mut total_saliency = 0.0;
+    let mut emphasis = 0.0;
+    for y in 0..height {
+        for x in 0..width {
+            let saliency = (luminances[y * width + x] - mean).abs();
+            if saliency == 0.0 {
+                continue;
+            }
+            let nearest = power_points
+                .iter()
+                .map(|(px, py)| (x as f64 - px).powi(2) + (y as f64 - py).powi(2))
+                .fold(f64::INFINITY, f64::min);
+            total_saliency += saliency;
+            emphasis += saliency * (-nearest / (2.0 * sigma * sigma)).exp();
+        }
+    }
+
+    if total_saliency == 0.0 {
+        return 0.0;
+    }
+    emphasis / total_saliency
+}
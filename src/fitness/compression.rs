@@ -0,0 +1,78 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{ColorType, ImageFormat, RgbaImage};
+
+use crate::draw::evaluate_concur;
+use crate::vector::Environment;
+
+#[derive(Clone)]
+pub struct CompressionParams {
+    pub jpeg_quality: u8,
+    pub target_image_complexity: f64,
+    pub target_complexity_ratio: f64,
+}
+
+impl Default for CompressionParams {
+    fn default() -> Self {
+        Self {
+            jpeg_quality: 75,
+            target_image_complexity: 1.0,
+            target_complexity_ratio: 2.0,
+        }
+    }
+}
+
+pub fn calculate_fitness(env: &mut Environment, idx: usize) {
+    let fitness_values: Vec<f64> = evaluate_concur(env, |image| calculate_compression(&env.fitness_params.compression, image));
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+// Machado and Cardoso style estimate: the image complexity (IC) is the JPEG error divided by
+// the JPEG compression ratio and the processing complexity (PC) is the lossless PNG size ratio.
+// the fitness is the log distance of IC and IC/PC to their targets, so both scales weigh the same
+fn calculate_compression(params: &CompressionParams, image: &RgbaImage) -> f64 {
+    let image_complexity = jpeg_complexity(image, params.jpeg_quality);
+    let processing_complexity = png_complexity(image);
+    let complexity_ratio = image_complexity / processing_complexity;
+
+    log_distance(image_complexity, params.target_image_complexity)
+        + log_distance(complexity_ratio, params.target_complexity_ratio)
+}
+
+fn log_distance(value: f64, target: f64) -> f64 {
+    let value = value.max(f64::EPSILON);
+    let target = target.max(f64::EPSILON);
+    (value / target).ln().abs()
+}
+
+fn jpeg_complexity(image: &RgbaImage, quality: u8) -> f64 {
+    let rgb: Vec<u8> = image.as_raw().chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect();
+
+    let mut encoded = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded, quality.clamp(1, 100))
+        .encode(&rgb, image.width(), image.height(), ColorType::Rgb8)
+        .unwrap();
+    let decoded = image::load_from_memory_with_format(&encoded, ImageFormat::Jpeg)
+        .unwrap()
+        .to_rgb8();
+
+    let squared_error: f64 = rgb.iter().zip(decoded.as_raw().iter()).map(|(a, b)| {
+        let diff = *a as f64 - *b as f64;
+        diff * diff
+    }).sum();
+    let rmse = (squared_error / rgb.len() as f64).sqrt();
+    let compression_ratio = rgb.len() as f64 / encoded.len() as f64;
+
+    rmse / compression_ratio
+}
+
+fn png_complexity(image: &RgbaImage) -> f64 {
+    let mut encoded = Vec::new();
+    PngEncoder::new(&mut encoded)
+        .encode(image.as_raw(), image.width(), image.height(), ColorType::Rgba8)
+        .unwrap();
+
+    encoded.len() as f64 / image.as_raw().len() as f64
+}
@@ -1,51 +1,31 @@
 use image::{RgbaImage, imageops};
-use rayon::prelude::*;
-use std::sync::{Arc, Mutex};
-use crate::draw::draw_concur;
+use crate::draw::evaluate_concur;
 use crate::vector::Environment;
 
-pub fn calculate_fitness(env: &mut Environment, idx: usize) -> () {
-    let images: Vec<RgbaImage> = draw_concur(
-        &env.pool,
-        env.target_width,
-        env.target_height,
-        env.num_threads,
-    );
-    let fitness_values: Vec<f64> = calculate_contrast_concur(&images);
+pub fn calculate_fitness(env: &mut Environment, idx: usize) {
+    let fitness_values: Vec<f64> = evaluate_concur(env, calculate_contrast);
     for (i, fitness) in fitness_values.into_iter().enumerate() {
         env.pool[i].fitness[idx] = fitness;
     }
 }
 
-fn calculate_contrast_concur(images: &Vec<RgbaImage>) -> Vec<f64> {
-    let fitness_values: Arc<Mutex<Vec<Option<f64>>>> = Arc::new(Mutex::new(vec![None; images.len()]));
-
-    images.par_iter().enumerate().for_each(|(i, image)| {
-        let fitness = calculate_contrast(image);
-        let mut fitness_output = fitness_values.lock().unwrap();
-        fitness_output[i] = Some(fitness);
-    });
-
-    Arc::try_unwrap(fitness_values)
-        .unwrap()
-        .into_inner()
-        .unwrap()
-        .into_iter()
-        .map(|fitness| fitness.unwrap())
-        .collect()
-}
-
 fn calculate_contrast(image: &RgbaImage) -> f64 {
     let mut total_contrast = 0.0;
     let mut weight = 1.0;
 
-    let mut current_image = image.clone();
+    // the first level is scaled straight from the borrowed image
+    let mut current_image: Option<RgbaImage> = None;
     for _ in 0..5 {
         // Downscale the image
+        let source = current_image.as_ref().unwrap_or(image);
+        // small evaluation resolutions run out of levels before the fifth
+        if source.width() < 2 || source.height() < 2 {
+            break;
+        }
         let scaled_image = imageops::resize(
-            &current_image,
-            current_image.width() / 2,
-            current_image.height() / 2,
+            source,
+            source.width() / 2,
+            source.height() / 2,
             imageops::FilterType::Triangle,
         );
 
@@ -54,7 +34,7 @@ fn calculate_contrast(image: &RgbaImage) -> f64 {
         total_contrast += weight * contrast;
 
         // Prepare for the next iteration
-        current_image = scaled_image;
+        current_image = Some(scaled_image);
         weight *= 0.5; // Decrease weight for smaller scales
     }
 
@@ -86,5 +66,5 @@ fn compute_luminance_contrast(image: &RgbaImage) -> f64 {
         }
     }
 
-    -(contrast_sum / luminances.len() as f64)
+    contrast_sum / luminances.len() as f64
 }
@@ -0,0 +1,228 @@
+use image::{imageops, GrayImage, RgbaImage};
+
+use crate::draw::evaluate_concur;
+use crate::vector::Environment;
+
+#[derive(Clone)]
+pub struct DeltaEParams {
+    pub ignore_alpha: bool,
+    pub mask: Option<GrayImage>,
+}
+
+impl Default for DeltaEParams {
+    fn default() -> Self {
+        Self {
+            ignore_alpha: true,
+            mask: None,
+        }
+    }
+}
+
+pub fn calculate_fitness(env: &mut Environment, idx: usize) {
+    let params = &env.fitness_params.delta_e;
+    let target_lab = image_to_lab(&env.eval_img, params.ignore_alpha);
+    let weights = mask_weights(params.mask.as_ref(), env.eval_width, env.eval_height);
+    let fitness_values: Vec<f64> = evaluate_concur(env, |image| calculate_mean_delta_e(&target_lab, &weights, params.ignore_alpha, image));
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+fn calculate_mean_delta_e(target_lab: &[[f64; 3]], weights: &[f64], ignore_alpha: bool, image: &RgbaImage) -> f64 {
+    let mut weighted_sum = 0.0;
+    let mut weight_total = 0.0;
+
+    for ((pixel, lab), weight) in image.pixels().zip(target_lab.iter()).zip(weights.iter()) {
+        if *weight == 0.0 {
+            continue;
+        }
+        let pixel_lab = rgba_to_lab(pixel.0, ignore_alpha);
+        weighted_sum += weight * ciede2000(lab, &pixel_lab);
+        weight_total += weight;
+    }
+
+    if weight_total == 0.0 {
+        return 0.0;
+    }
+    weighted_sum / weight_total
+}
+
+// every pixel weighs 1.0 without a mask, otherwise the mask luminance scaled to [0, 1]
+fn mask_weights(mask: Option<&GrayImage>, width: u32, height: u32) -> Vec<f64> {
+    match mask {
+        None => vec![1.0; (width * height) as usize],
+        Some(mask) => {
+            let resized;
+            let mask = if mask.width() != width || mask.height() != height {
+                resized = imageops::resize(mask, width, height, imageops::FilterType::Triangle);
+                &resized
+            } else {
+                mask
+            };
+            mask.pixels().map(|pixel| pixel.0[0] as f64 / 255.0).collect()
+        }
+    }
+}
+
+fn image_to_lab(image: &RgbaImage, ignore_alpha: bool) -> Vec<[f64; 3]> {
+    image.pixels().map(|pixel| rgba_to_lab(pixel.0, ignore_alpha)).collect()
+}
+
+// when alpha is taken into account the color is composited over black
+pub fn rgba_to_lab(rgba: [u8; 4], ignore_alpha: bool) -> [f64; 3] {
+    let alpha = if ignore_alpha { 1.0 } else { rgba[3] as f64 / 255.0 };
+    let r = srgb_to_linear(rgba[0]) * alpha;
+    let g = srgb_to_linear(rgba[1]) * alpha;
+    let b = srgb_to_linear(rgba[2]) * alpha;
+
+    // linear sRGB to XYZ, normalised by the D65 white point
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+
+    let fx = lab_f(x);
+    let fy = lab_f(y);
+    let fz = lab_f(z);
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn srgb_to_linear(channel: u8) -> f64 {
+    let c = channel as f64 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn lab_f(t: f64) -> f64 {
+    if t > 0.008856 {
+        t.cbrt()
+    } else {
+        7.787 * t + 16.0 / 116.0
+    }
+}
+
+// Sharma, Wu and Dalal formulation of CIEDE2000 with kL = kC = kH = 1
+pub fn ciede2000(lab1: &[f64; 3], lab2: &[f64; 3]) -> f64 {
+    let [l1, a1, b1] = *lab1;
+    let [l2, a2, b2] = *lab2;
+
+    let c_bar = ((a1 * a1 + b1 * b1).sqrt() + (a2 * a2 + b2 * b2).sqrt()) / 2.0;
+    let c_bar7 = c_bar.powi(7);
+    let g = 0.5 * (1.0 - (c_bar7 / (c_bar7 + 25f64.powi(7))).sqrt());
+
+    let a1p = (1.0 + g) * a1;
+    let a2p = (1.0 + g) * a2;
+    let c1p = (a1p * a1p + b1 * b1).sqrt();
+    let c2p = (a2p * a2p + b2 * b2).sqrt();
+    let h1p = hue_angle(b1, a1p);
+    let h2p = hue_angle(b2, a2p);
+
+    let delta_lp = l2 - l1;
+    let delta_cp = c2p - c1p;
+    let delta_hp = if c1p * c2p == 0.0 {
+        0.0
+    } else if (h2p - h1p).abs() <= 180.0 {
+        h2p - h1p
+    } else if h2p - h1p > 180.0 {
+        h2p - h1p - 360.0
+    } else {
+        h2p - h1p + 360.0
+    };
+    let delta_big_hp = 2.0 * (c1p * c2p).sqrt() * (delta_hp.to_radians() / 2.0).sin();
+
+    let l_bar_p = (l1 + l2) / 2.0;
+    let c_bar_p = (c1p + c2p) / 2.0;
+    let h_bar_p = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= 180.0 {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 360.0 {
+        (h1p + h2p + 360.0) / 2.0
+    } else {
+        (h1p + h2p - 360.0) / 2.0
+    };
+
+    let t = 1.0
+        - 0.17 * (h_bar_p - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar_p).to_radians().cos()
+        + 0.32 * (3.0 * h_bar_p + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar_p - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_bar_p - 275.0) / 25.0).powi(2)).exp();
+    let c_bar_p7 = c_bar_p.powi(7);
+    let r_c = 2.0 * (c_bar_p7 / (c_bar_p7 + 25f64.powi(7))).sqrt();
+    let l_offset = (l_bar_p - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l_offset / (20.0 + l_offset).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar_p;
+    let s_h = 1.0 + 0.015 * c_bar_p * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let l_term = delta_lp / s_l;
+    let c_term = delta_cp / s_c;
+    let h_term = delta_big_hp / s_h;
+
+    (l_term * l_term + c_term * c_term + h_term * h_term + r_t * c_term * h_term).sqrt()
+}
+
+fn hue_angle(b: f64, a_prime: f64) -> f64 {
+    if b == 0.0 && a_prime == 0.0 {
+        return 0.0;
+    }
+    let angle = b.atan2(a_prime).to_degrees();
+    if angle < 0.0 { angle + 360.0 } else { angle }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // L1, a1, b1, L2, a2, b2 and the difference, from the test data of Sharma, Wu and Dalal
+    const SHARMA_PAIRS: [[f64; 7]; 34] = [
+        [50.0, 2.6772, -79.7751, 50.0, 0.0, -82.7485, 2.0425],
+        [50.0, 3.1571, -77.2803, 50.0, 0.0, -82.7485, 2.8615],
+        [50.0, 2.8361, -74.0200, 50.0, 0.0, -82.7485, 3.4412],
+        [50.0, -1.3802, -84.2814, 50.0, 0.0, -82.7485, 1.0000],
+        [50.0, -1.1848, -84.8006, 50.0, 0.0, -82.7485, 1.0000],
+        [50.0, -0.9009, -85.5211, 50.0, 0.0, -82.7485, 1.0000],
+        [50.0, 0.0, 0.0, 50.0, -1.0, 2.0, 2.3669],
+        [50.0, -1.0, 2.0, 50.0, 0.0, 0.0, 2.3669],
+        [50.0, 2.4900, -0.0010, 50.0, -2.4900, 0.0009, 7.1792],
+        [50.0, 2.4900, -0.0010, 50.0, -2.4900, 0.0010, 7.1792],
+        [50.0, 2.4900, -0.0010, 50.0, -2.4900, 0.0011, 7.2195],
+        [50.0, 2.4900, -0.0010, 50.0, -2.4900, 0.0012, 7.2195],
+        [50.0, -0.0010, 2.4900, 50.0, 0.0009, -2.4900, 4.8045],
+        [50.0, -0.0010, 2.4900, 50.0, 0.0010, -2.4900, 4.8045],
+        [50.0, -0.0010, 2.4900, 50.0, 0.0011, -2.4900, 4.7461],
+        [50.0, 2.5, 0.0, 50.0, 0.0, -2.5, 4.3065],
+        [50.0, 2.5, 0.0, 73.0, 25.0, -18.0, 27.1492],
+        [50.0, 2.5, 0.0, 61.0, -5.0, 29.0, 22.8977],
+        [50.0, 2.5, 0.0, 56.0, -27.0, -3.0, 31.9030],
+        [50.0, 2.5, 0.0, 58.0, 24.0, 15.0, 19.4535],
+        [50.0, 2.5, 0.0, 50.0, 3.1736, 0.5854, 1.0000],
+        [50.0, 2.5, 0.0, 50.0, 3.2972, 0.0, 1.0000],
+        [50.0, 2.5, 0.0, 50.0, 1.8634, 0.5757, 1.0000],
+        [50.0, 2.5, 0.0, 50.0, 3.2592, 0.3350, 1.0000],
+        [60.2574, -34.0099, 36.2677, 60.4626, -34.1751, 39.4387, 1.2644],
+        [63.0109, -31.0961, -5.8663, 62.8187, -29.7946, -4.0864, 1.2630],
+        [61.2901, 3.7196, -5.3901, 61.4292, 2.2480, -4.9620, 1.8731],
+        [35.0831, -44.1164, 3.7933, 35.0232, -40.0716, 1.5901, 1.8645],
+        [22.7233, 20.0904, -46.6940, 23.0331, 14.9730, -42.5619, 2.0373],
+        [36.4612, 47.8580, 18.3852, 36.2715, 50.5065, 21.2231, 1.4146],
+        [90.8027, -2.0831, 1.4410, 91.1528, -1.6435, 0.0447, 1.4441],
+        [90.9257, -0.5406, -0.9208, 88.6381, -0.8985, -0.7239, 1.5381],
+        [6.7747, -0.2908, -2.4247, 5.8714, -0.0985, -2.2286, 0.6377],
+        [2.0776, 0.0795, -1.1350, 0.9033, -0.0636, -0.5514, 0.9082],
+    ];
+
+    #[test]
+    fn ciede2000_matches_the_sharma_test_data() {
+        for [l1, a1, b1, l2, a2, b2, expected] in SHARMA_PAIRS {
+            let (lab1, lab2) = ([l1, a1, b1], [l2, a2, b2]);
+            assert!((ciede2000(&lab1, &lab2) - expected).abs() < 1e-4, "{:?} {:?}", lab1, lab2);
+            assert!((ciede2000(&lab2, &lab1) - expected).abs() < 1e-4, "{:?} {:?}", lab2, lab1);
+        }
+        assert_eq!(ciede2000(&[50.0, 2.5, 0.0], &[50.0, 2.5, 0.0]), 0.0);
+    }
+}
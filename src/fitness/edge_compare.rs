@@ -0,0 +1,280 @@
+use image::RgbaImage;
+use std::collections::VecDeque;
+
+use crate::draw::evaluate_concur;
+use crate::vector::Environment;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum EdgeMode {
+    Gradient,
+    Canny,
+}
+
+#[derive(Clone)]
+pub struct EdgeCompareParams {
+    pub mode: EdgeMode,
+    pub orientation_weight: f64,
+    pub low_threshold: f64,
+    pub high_threshold: f64,
+}
+
+impl Default for EdgeCompareParams {
+    fn default() -> Self {
+        Self {
+            mode: EdgeMode::Gradient,
+            orientation_weight: 1.0,
+            low_threshold: 40.0,
+            high_threshold: 100.0,
+        }
+    }
+}
+
+struct EdgeMaps {
+    magnitudes: Vec<f64>,
+    orientations: Vec<f64>,
+    edges: Vec<bool>,
+    distances: Vec<f64>,
+}
+
+pub fn calculate_fitness(env: &mut Environment, idx: usize) {
+    let params = &env.fitness_params.edge_compare;
+    let target_maps = edge_maps(&env.eval_img, params);
+    let fitness_values: Vec<f64> = evaluate_concur(
+        env,
+        |image| {
+            let maps = edge_maps(image, params);
+            match params.mode {
+                EdgeMode::Gradient => gradient_difference(&target_maps, &maps, params.orientation_weight),
+                EdgeMode::Canny => chamfer_distance(&target_maps, &maps, image.width(), image.height()),
+            }
+        }
+    );
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+fn edge_maps(image: &RgbaImage, params: &EdgeCompareParams) -> EdgeMaps {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let luminances: Vec<f64> = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, _] = pixel.0;
+            0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
+        })
+        .collect();
+
+    match params.mode {
+        EdgeMode::Gradient => {
+            let (gx, gy) = sobel(&luminances, width, height);
+            EdgeMaps {
+                magnitudes: gx.iter().zip(gy.iter()).map(|(x, y)| (x * x + y * y).sqrt()).collect(),
+                orientations: gx.iter().zip(gy.iter()).map(|(x, y)| y.atan2(*x)).collect(),
+                edges: Vec::new(),
+                distances: Vec::new(),
+            }
+        }
+        EdgeMode::Canny => {
+            let edges = canny(&luminances, width, height, params.low_threshold, params.high_threshold);
+            let distances = distance_transform(&edges, width, height);
+            EdgeMaps {
+                magnitudes: Vec::new(),
+                orientations: Vec::new(),
+                edges,
+                distances,
+            }
+        }
+    }
+}
+
+// mean normalised magnitude difference plus the orientation mismatch where both images have
+// a gradient, using doubled angles so edge polarity (and therefore color) is ignored
+fn gradient_difference(target: &EdgeMaps, maps: &EdgeMaps, orientation_weight: f64) -> f64 {
+    let max_magnitude = 4.0 * 255.0 * 2f64.sqrt();
+    let mut magnitude_difference = 0.0;
+    let mut orientation_difference = 0.0;
+    let mut total_weight = 0.0;
+
+    for i in 0..target.magnitudes.len() {
+        magnitude_difference += (target.magnitudes[i] - maps.magnitudes[i]).abs() / max_magnitude;
+
+        let weight = (target.magnitudes[i] * maps.magnitudes[i]).sqrt() / max_magnitude;
+        if weight > 0.0 {
+            let angle = 2.0 * (target.orientations[i] - maps.orientations[i]);
+            orientation_difference += weight * (1.0 - angle.cos()) / 2.0;
+            total_weight += weight;
+        }
+    }
+
+    let mut difference = magnitude_difference / target.magnitudes.len() as f64;
+    if total_weight > 0.0 {
+        difference += orientation_weight * orientation_difference / total_weight;
+    }
+    difference
+}
+
+// symmetric mean chamfer distance between the two edge maps, in pixels
+fn chamfer_distance(target: &EdgeMaps, maps: &EdgeMaps, width: u32, height: u32) -> f64 {
+    let diagonal = ((width * width + height * height) as f64).sqrt();
+    let directed = |from: &EdgeMaps, to: &EdgeMaps| -> Option<f64> {
+        let mut total = 0.0;
+        let mut count = 0;
+        for (i, &edge) in from.edges.iter().enumerate() {
+            if edge {
+                total += to.distances[i].min(diagonal);
+                count += 1;
+            }
+        }
+        if count == 0 { None } else { Some(total / count as f64) }
+    };
+
+    match (directed(maps, target), directed(target, maps)) {
+        (Some(forward), Some(backward)) => (forward + backward) / 2.0,
+        (None, None) => 0.0,
+        _ => diagonal,
+    }
+}
+
+// 3x3 sobel derivatives with replicated borders
+pub fn sobel(luminances: &[f64], width: usize, height: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut gx = vec![0.0; width * height];
+    let mut gy = vec![0.0; width * height];
+    let at = |x: isize, y: isize| {
+        let x = x.clamp(0, width as isize - 1) as usize;
+        let y = y.clamp(0, height as isize - 1) as usize;
+        luminances[y * width + x]
+    };
+
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let i = y as usize * width + x as usize;
+            gx[i] = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+            gy[i] = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+        }
+    }
+    (gx, gy)
+}
+
+fn canny(luminances: &[f64], width: usize, height: usize, low_threshold: f64, high_threshold: f64) -> Vec<bool> {
+    let blurred = gaussian_blur(luminances, width, height);
+    let (gx, gy) = sobel(&blurred, width, height);
+    let magnitudes: Vec<f64> = gx.iter().zip(gy.iter()).map(|(x, y)| (x * x + y * y).sqrt()).collect();
+
+    // non maximum suppression along the gradient direction rounded to 45 degrees
+    let mut suppressed = vec![0.0; width * height];
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let i = y * width + x;
+            let angle = gy[i].atan2(gx[i]).to_degrees().rem_euclid(180.0);
+            let (dx, dy): (isize, isize) = if !(22.5..157.5).contains(&angle) {
+                (1, 0)
+            } else if angle < 67.5 {
+                (1, 1)
+            } else if angle < 112.5 {
+                (0, 1)
+            } else {
+                (-1, 1)
+            };
+            let ahead = magnitudes[(y as isize + dy) as usize * width + (x as isize + dx) as usize];
+            let behind = magnitudes[(y as isize - dy) as usize * width + (x as isize - dx) as usize];
+            if magnitudes[i] >= ahead && magnitudes[i] >= behind {
+                suppressed[i] = magnitudes[i];
+            }
+        }
+    }
+
+    // hysteresis, weak edges are kept only when connected to a strong one
+    let mut edges = vec![false; width * height];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for (i, &magnitude) in suppressed.iter().enumerate() {
+        if magnitude >= high_threshold {
+            edges[i] = true;
+            queue.push_back(i);
+        }
+    }
+    while let Some(i) = queue.pop_front() {
+        let (x, y) = ((i % width) as isize, (i / width) as isize);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    continue;
+                }
+                let j = ny as usize * width + nx as usize;
+                if !edges[j] && suppressed[j] >= low_threshold {
+                    edges[j] = true;
+                    queue.push_back(j);
+                }
+            }
+        }
+    }
+    edges
+}
+
+// separable 5x5 gaussian with sigma 1.4 and replicated borders
+fn gaussian_blur(luminances: &[f64], width: usize, height: usize) -> Vec<f64> {
+    let kernel = [0.1201, 0.2339, 0.2920, 0.2339, 0.1201];
+    let mut horizontal = vec![0.0; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            for (k, weight) in kernel.iter().enumerate() {
+                let sx = (x as isize + k as isize - 2).clamp(0, width as isize - 1) as usize;
+                sum += weight * luminances[y * width + sx];
+            }
+            horizontal[y * width + x] = sum;
+        }
+    }
+
+    let mut blurred = vec![0.0; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            for (k, weight) in kernel.iter().enumerate() {
+                let sy = (y as isize + k as isize - 2).clamp(0, height as isize - 1) as usize;
+                sum += weight * horizontal[sy * width + x];
+            }
+            blurred[y * width + x] = sum;
+        }
+    }
+    blurred
+}
+
+// two pass chamfer distance transform with unit and diagonal steps
+fn distance_transform(edges: &[bool], width: usize, height: usize) -> Vec<f64> {
+    let diagonal_step = 2f64.sqrt();
+    let mut distances: Vec<f64> = edges.iter().map(|&e| if e { 0.0 } else { f64::INFINITY }).collect();
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let mut d = distances[i];
+            if x > 0 { d = d.min(distances[i - 1] + 1.0); }
+            if y > 0 {
+                d = d.min(distances[i - width] + 1.0);
+                if x > 0 { d = d.min(distances[i - width - 1] + diagonal_step); }
+                if x + 1 < width { d = d.min(distances[i - width + 1] + diagonal_step); }
+            }
+            distances[i] = d;
+        }
+    }
+
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            let i = y * width + x;
+            let mut d = distances[i];
+            if x + 1 < width { d = d.min(distances[i + 1] + 1.0); }
+            if y + 1 < height {
+                d = d.min(distances[i + width] + 1.0);
+                if x + 1 < width { d = d.min(distances[i + width + 1] + diagonal_step); }
+                if x > 0 { d = d.min(distances[i + width - 1] + diagonal_step); }
+            }
+            distances[i] = d;
+        }
+    }
+    distances
+}
@@ -0,0 +1,117 @@
+use image::RgbaImage;
+
+use crate::draw::evaluate_concur;
+use crate::fitness::edge_compare::sobel;
+use crate::vector::Environment;
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum FractalSource {
+    Edges,
+    Luminance,
+}
+
+#[derive(Clone)]
+pub struct FractalParams {
+    pub target_dimension: f64,
+    pub source: FractalSource,
+    pub edge_threshold: f64,
+    pub luminance_threshold: f64,
+}
+
+impl Default for FractalParams {
+    fn default() -> Self {
+        Self {
+            target_dimension: 1.4,
+            source: FractalSource::Edges,
+            edge_threshold: 64.0,
+            luminance_threshold: 128.0,
+        }
+    }
+}
+
+pub fn calculate_fitness(env: &mut Environment, idx: usize) {
+    let fitness_values: Vec<f64> = evaluate_concur(env, |image| calculate_fractal(&env.fitness_params.fractal, image));
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+fn calculate_fractal(params: &FractalParams, image: &RgbaImage) -> f64 {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let luminances: Vec<f64> = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, _] = pixel.0;
+            0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
+        })
+        .collect();
+
+    let binary = match params.source {
+        FractalSource::Edges => edge_map(&luminances, width, height, params.edge_threshold),
+        FractalSource::Luminance => luminances.iter().map(|&l| l > params.luminance_threshold).collect(),
+    };
+
+    (box_counting_dimension(&binary, width, height) - params.target_dimension).abs()
+}
+
+// the border pixels are never edges, the replicated borders of `sobel` would find some along the
+// frame
+fn edge_map(luminances: &[f64], width: usize, height: usize, threshold: f64) -> Vec<bool> {
+    let mut edges = vec![false; width * height];
+    if width < 3 || height < 3 {
+        return edges;
+    }
+
+    let (gx, gy) = sobel(luminances, width, height);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let i = y * width + x;
+            edges[i] = (gx[i] * gx[i] + gy[i] * gy[i]).sqrt() > threshold;
+        }
+    }
+    edges
+}
+
+// slope of log(occupied boxes) against log(1 / box size) for power of two box sizes
+fn box_counting_dimension(binary: &[bool], width: usize, height: usize) -> f64 {
+    let mut log_inverse_sizes = Vec::new();
+    let mut log_counts = Vec::new();
+
+    let mut box_size = 1;
+    while box_size <= width.min(height) / 2 {
+        let boxes_x = width.div_ceil(box_size);
+        let boxes_y = height.div_ceil(box_size);
+        let mut occupied = vec![false; boxes_x * boxes_y];
+        for y in 0..height {
+            for x in 0..width {
+                if binary[y * width + x] {
+                    occupied[(y / box_size) * boxes_x + x / box_size] = true;
+                }
+            }
+        }
+
+        let count = occupied.iter().filter(|&&o| o).count();
+        if count > 0 {
+            log_inverse_sizes.push(-(box_size as f64).ln());
+            log_counts.push((count as f64).ln());
+        }
+        box_size *= 2;
+    }
+
+    if log_counts.len() < 2 {
+        return 0.0;
+    }
+
+    let n = log_counts.len() as f64;
+    let mean_x = log_inverse_sizes.iter().sum::<f64>() / n;
+    let mean_y = log_counts.iter().sum::<f64>() / n;
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for (x, y) in log_inverse_sizes.iter().zip(log_counts.iter()) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance += (x - mean_x).powi(2);
+    }
+    covariance / variance
+}
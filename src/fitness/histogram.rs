@@ -0,0 +1,123 @@
+use image::RgbaImage;
+
+use crate::draw::evaluate_concur;
+use crate::vector::Environment;
+
+#[derive(Clone)]
+pub struct HistogramParams {
+    pub bins_per_channel: usize,
+    pub spread_percentile: f64,
+}
+
+impl Default for HistogramParams {
+    fn default() -> Self {
+        Self {
+            bins_per_channel: 8,
+            spread_percentile: 0.01,
+        }
+    }
+}
+
+pub fn calculate_luminance_entropy(env: &mut Environment, idx: usize) {
+    calculate_fitness(env, idx, |image| entropy(&luminance_histogram(image)));
+}
+
+pub fn calculate_color_entropy(env: &mut Environment, idx: usize) {
+    let bins = env.fitness_params.histogram.bins_per_channel;
+    calculate_fitness(env, idx, |image| entropy(&color_histogram(image, bins)));
+}
+
+pub fn calculate_spread(env: &mut Environment, idx: usize) {
+    let percentile = env.fitness_params.histogram.spread_percentile;
+    calculate_fitness(env, idx, |image| spread(&luminance_histogram(image), percentile));
+}
+
+pub fn calculate_distance(env: &mut Environment, idx: usize) {
+    let bins = env.fitness_params.histogram.bins_per_channel;
+    let target_histogram = color_histogram(&env.eval_img, bins);
+    calculate_fitness(env, idx, |image| hellinger_distance(&target_histogram, &color_histogram(image, bins)));
+}
+
+fn calculate_fitness(env: &mut Environment, idx: usize, measure: impl Fn(&RgbaImage) -> f64 + Sync) {
+    let fitness_values: Vec<f64> = evaluate_concur(env, measure);
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+// normalised 256 bin histogram of the luminance
+fn luminance_histogram(image: &RgbaImage) -> Vec<f64> {
+    let mut histogram = vec![0.0; 256];
+    for pixel in image.pixels() {
+        let [r, g, b, _] = pixel.0;
+        let luminance = (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64) as usize;
+        histogram[luminance.min(255)] += 1.0;
+    }
+    normalise(histogram)
+}
+
+// normalised joint RGB histogram with `bins` levels per channel
+fn color_histogram(image: &RgbaImage, bins: usize) -> Vec<f64> {
+    let bins = bins.clamp(1, 256);
+    let mut histogram = vec![0.0; bins * bins * bins];
+    for pixel in image.pixels() {
+        let [r, g, b, _] = pixel.0;
+        let quantise = |c: u8| c as usize * bins / 256;
+        histogram[(quantise(r) * bins + quantise(g)) * bins + quantise(b)] += 1.0;
+    }
+    normalise(histogram)
+}
+
+fn normalise(mut histogram: Vec<f64>) -> Vec<f64> {
+    let total: f64 = histogram.iter().sum();
+    if total > 0.0 {
+        histogram.iter_mut().for_each(|h| *h /= total);
+    }
+    histogram
+}
+
+// shannon entropy in bits
+fn entropy(histogram: &[f64]) -> f64 {
+    histogram
+        .iter()
+        .filter(|&&p| p > 0.0)
+        .map(|&p| -p * p.log2())
+        .sum()
+}
+
+// width of the luminance range left after cutting `percentile` of the pixels from each end,
+// scaled to [0, 1]
+fn spread(histogram: &[f64], percentile: f64) -> f64 {
+    let percentile = percentile.clamp(0.0, 0.5);
+    let mut cumulative = 0.0;
+    let mut low = 0;
+    for (i, p) in histogram.iter().enumerate() {
+        cumulative += p;
+        if cumulative > percentile {
+            low = i;
+            break;
+        }
+    }
+
+    cumulative = 0.0;
+    let mut high = histogram.len() - 1;
+    for (i, p) in histogram.iter().enumerate().rev() {
+        cumulative += p;
+        if cumulative > percentile {
+            high = i;
+            break;
+        }
+    }
+
+    high.saturating_sub(low) as f64 / (histogram.len() - 1) as f64
+}
+
+// 0 for identical histograms and 1 for histograms without any overlap
+fn hellinger_distance(histogram1: &[f64], histogram2: &[f64]) -> f64 {
+    let bhattacharyya: f64 = histogram1
+        .iter()
+        .zip(histogram2.iter())
+        .map(|(p, q)| (p * q).sqrt())
+        .sum();
+    (1.0 - bhattacharyya).max(0.0).sqrt()
+}
@@ -0,0 +1,237 @@
+use image::RgbaImage;
+use std::f64::consts::PI;
+
+use crate::draw::evaluate_concur;
+use crate::vector::Environment;
+
+#[derive(Clone)]
+pub struct SpectralParams {
+    pub target_slope: f64,
+}
+
+impl Default for SpectralParams {
+    fn default() -> Self {
+        Self {
+            target_slope: -2.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+pub fn calculate_fitness(env: &mut Environment, idx: usize) {
+    let target_slope = env.fitness_params.spectral.target_slope;
+    let fitness_values: Vec<f64> = evaluate_concur(env, |image| (spectral_slope(image) - target_slope).abs());
+    for (i, fitness) in fitness_values.into_iter().enumerate() {
+        env.pool[i].fitness[idx] = fitness;
+    }
+}
+
+// slope of the log-log radially averaged power spectrum of the luminance, natural images sit
+// around -2. the luminance is mean subtracted, hann windowed and zero padded to a power of two
+fn spectral_slope(image: &RgbaImage) -> f64 {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let luminances: Vec<f64> = image
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, _] = pixel.0;
+            0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
+        })
+        .collect();
+    let mean = luminances.iter().sum::<f64>() / luminances.len() as f64;
+
+    let padded_width = width.next_power_of_two();
+    let padded_height = height.next_power_of_two();
+    let mut spectrum = vec![Complex { re: 0.0, im: 0.0 }; padded_width * padded_height];
+    for y in 0..height {
+        let window_y = hann(y, height);
+        for x in 0..width {
+            spectrum[y * padded_width + x].re = (luminances[y * width + x] - mean) * window_y * hann(x, width);
+        }
+    }
+    fft_2d(&mut spectrum, padded_width, padded_height);
+
+    // radial bins in units of the shorter padded side, up to the nyquist frequency
+    let size = padded_width.min(padded_height);
+    let num_bins = size / 2;
+    let mut power_sums = vec![0.0; num_bins + 1];
+    let mut counts = vec![0usize; num_bins + 1];
+    for v in 0..padded_height {
+        let fy = frequency(v, padded_height);
+        for u in 0..padded_width {
+            let fx = frequency(u, padded_width);
+            let bin = ((fx * fx + fy * fy).sqrt() * size as f64).round() as usize;
+            if bin == 0 || bin > num_bins {
+                continue;
+            }
+            let c = spectrum[v * padded_width + u];
+            power_sums[bin] += c.re * c.re + c.im * c.im;
+            counts[bin] += 1;
+        }
+    }
+
+    let mut log_frequencies = Vec::with_capacity(num_bins);
+    let mut log_powers = Vec::with_capacity(num_bins);
+    for bin in 1..=num_bins {
+        if counts[bin] == 0 || power_sums[bin] <= 0.0 {
+            continue;
+        }
+        log_frequencies.push((bin as f64).ln());
+        log_powers.push((power_sums[bin] / counts[bin] as f64).ln());
+    }
+
+    if log_powers.len() < 2 {
+        return 0.0;
+    }
+
+    let n = log_powers.len() as f64;
+    let mean_x = log_frequencies.iter().sum::<f64>() / n;
+    let mean_y = log_powers.iter().sum::<f64>() / n;
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for (x, y) in log_frequencies.iter().zip(log_powers.iter()) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance += (x - mean_x).powi(2);
+    }
+    covariance / variance
+}
+
+fn hann(i: usize, n: usize) -> f64 {
+    if n < 2 {
+        return 1.0;
+    }
+    0.5 - 0.5 * (2.0 * PI * i as f64 / (n - 1) as f64).cos()
+}
+
+// signed frequency in cycles per sample of the fft index `k`
+fn frequency(k: usize, n: usize) -> f64 {
+    if k <= n / 2 {
+        k as f64 / n as f64
+    } else {
+        (k as f64 - n as f64) / n as f64
+    }
+}
+
+fn fft_2d(data: &mut [Complex], width: usize, height: usize) {
+    for row in data.chunks_mut(width) {
+        fft(row);
+    }
+
+    let mut column = vec![Complex { re: 0.0, im: 0.0 }; height];
+    for x in 0..width {
+        for y in 0..height {
+            column[y] = data[y * width + x];
+        }
+        fft(&mut column);
+        for y in 0..height {
+            data[y * width + x] = column[y];
+        }
+    }
+}
+
+// iterative radix-2 cooley-tukey, the length must be a power of two
+fn fft(data: &mut [Complex]) {
+    let n = data.len();
+    if n < 2 {
+        return;
+    }
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f64;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(length) {
+            let mut twiddle = Complex { re: 1.0, im: 0.0 };
+            for k in 0..length / 2 {
+                let a = data[start + k];
+                let b = data[start + k + length / 2];
+                let t = Complex {
+                    re: b.re * twiddle.re - b.im * twiddle.im,
+                    im: b.re * twiddle.im + b.im * twiddle.re,
+                };
+                data[start + k] = Complex { re: a.re + t.re, im: a.im + t.im };
+                data[start + k + length / 2] = Complex { re: a.re - t.re, im: a.im - t.im };
+                twiddle = Complex {
+                    re: twiddle.re * w_re - twiddle.im * w_im,
+                    im: twiddle.re * w_im + twiddle.im * w_re,
+                };
+            }
+        }
+        length <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    // the O(n^2) transform the fft must agree with
+    fn dft(data: &[Complex]) -> Vec<Complex> {
+        let n = data.len();
+        (0..n)
+            .map(|k| {
+                data.iter().enumerate().fold(Complex { re: 0.0, im: 0.0 }, |sum, (t, x)| {
+                    let (sin, cos) = (-2.0 * PI * (k * t) as f64 / n as f64).sin_cos();
+                    Complex { re: sum.re + x.re * cos - x.im * sin, im: sum.im + x.re * sin + x.im * cos }
+                })
+            })
+            .collect()
+    }
+
+    fn random_signal(n: usize) -> Vec<Complex> {
+        let mut rng = rand::thread_rng();
+        (0..n).map(|_| Complex { re: rng.gen_range(-1.0..1.0), im: rng.gen_range(-1.0..1.0) }).collect()
+    }
+
+    fn assert_close(actual: &[Complex], expected: &[Complex]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a.re - e.re).abs() < 1e-9 && (a.im - e.im).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn fft_matches_the_dft() {
+        for n in [1, 2, 4, 8, 64, 256] {
+            let signal = random_signal(n);
+            let mut transformed = signal.clone();
+            fft(&mut transformed);
+            assert_close(&transformed, &dft(&signal));
+        }
+    }
+
+    #[test]
+    fn fft_2d_transforms_rows_then_columns() {
+        let (width, height) = (8, 4);
+        let signal = random_signal(width * height);
+        let mut transformed = signal.clone();
+        fft_2d(&mut transformed, width, height);
+
+        let mut expected: Vec<Complex> = signal.chunks(width).flat_map(dft).collect();
+        for x in 0..width {
+            let column: Vec<Complex> = (0..height).map(|y| expected[y * width + x]).collect();
+            for (y, value) in dft(&column).into_iter().enumerate() {
+                expected[y * width + x] = value;
+            }
+        }
+        assert_close(&transformed, &expected);
+    }
+}
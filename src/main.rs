@@ -1,12 +1,25 @@
 pub mod draw;
 pub mod vector;
+pub mod optimizer;
 pub mod fitness {
     pub mod pixel_compare;
     pub mod benford;
     pub mod contrast;
+    pub mod delta_e;
+    pub mod compression;
+    pub mod fractal;
+    pub mod composition;
+    pub mod color;
+    pub mod spectral;
+    pub mod bell_curve;
+    pub mod histogram;
+    pub mod edge_compare;
+    pub mod complexity;
 }
-pub use vector::{Environment, FitnessFunction, Vector, Triangle};
+pub use vector::{Direction, Environment, FitnessFunction, FitnessParams, Normalization, Alignment, BackgroundMode, BackgroundParams, GenomeParams, LayerCrossover, LocalSearchParams, LocalSearchTarget, Objective, PaintParams, ParentSelection, ResolutionSchedule, Scalarization, Vector, Triangle};
 
+use crate::draw::{BlendMode, RenderMode};
+use crate::optimizer::{OptimizerKind, OptimizerParams, SwarmTopology};
 use eframe::egui;
 use rfd::FileDialog;
 use std::time::Instant;
@@ -17,12 +30,44 @@ use std::collections::HashMap;
 use crate::fitness::pixel_compare;
 use crate::fitness::contrast;
 use crate::fitness::benford;
+use crate::fitness::delta_e;
+use crate::fitness::compression;
+use crate::fitness::fractal::{self, FractalSource};
+use crate::fitness::composition;
+use crate::fitness::color::{self, HarmonicTemplate};
+use crate::fitness::spectral;
+use crate::fitness::bell_curve;
+use crate::fitness::histogram;
+use crate::fitness::edge_compare::{self, EdgeMode};
+use crate::fitness::complexity;
 
-fn define_fitness_functions<'a>() -> HashMap<String, FitnessFunction<'a>> {
-    let mut fitness_registry: HashMap<String, FitnessFunction> = HashMap::new();
-    fitness_registry.insert("Pixel Compare".to_string(), Arc::new(pixel_compare::calculate_fitness) as FitnessFunction);
-    fitness_registry.insert("Contrast".to_string(), Arc::new(contrast::calculate_fitness) as FitnessFunction);
-    fitness_registry.insert("Benford".to_string(), Arc::new(benford::calculate_fitness) as FitnessFunction);
+fn define_fitness_functions<'a>() -> HashMap<String, Objective<'a>> {
+    let mut fitness_registry: HashMap<String, Objective> = HashMap::new();
+    let mut register = |name: &str, function: FitnessFunction<'a>, direction: Direction| {
+        fitness_registry.insert(name.to_string(), Objective::new(name, function, direction));
+    };
+    register("Pixel Compare", Arc::new(pixel_compare::calculate_fitness), Direction::Minimize);
+    register("Contrast", Arc::new(contrast::calculate_fitness), Direction::Maximize);
+    register("Benford", Arc::new(benford::calculate_fitness), Direction::Minimize);
+    register("Delta E 2000", Arc::new(delta_e::calculate_fitness), Direction::Minimize);
+    register("Compression Complexity", Arc::new(compression::calculate_fitness), Direction::Minimize);
+    register("Fractal Dimension", Arc::new(fractal::calculate_fitness), Direction::Minimize);
+    register("Horizontal Symmetry", Arc::new(composition::calculate_horizontal_symmetry), Direction::Minimize);
+    register("Vertical Symmetry", Arc::new(composition::calculate_vertical_symmetry), Direction::Minimize);
+    register("Rotational Symmetry", Arc::new(composition::calculate_rotational_symmetry), Direction::Minimize);
+    register("Visual Balance", Arc::new(composition::calculate_visual_balance), Direction::Minimize);
+    register("Rule of Thirds", Arc::new(composition::calculate_rule_of_thirds), Direction::Maximize);
+    register("Colorfulness", Arc::new(color::calculate_colorfulness), Direction::Maximize);
+    register("Color Harmony", Arc::new(color::calculate_harmony), Direction::Minimize);
+    register("Palette Distance", Arc::new(color::calculate_palette_distance), Direction::Minimize);
+    register("Spectral Slope", Arc::new(spectral::calculate_fitness), Direction::Minimize);
+    register("Bell Curve", Arc::new(bell_curve::calculate_fitness), Direction::Minimize);
+    register("Luminance Entropy", Arc::new(histogram::calculate_luminance_entropy), Direction::Maximize);
+    register("Color Entropy", Arc::new(histogram::calculate_color_entropy), Direction::Maximize);
+    register("Histogram Spread", Arc::new(histogram::calculate_spread), Direction::Maximize);
+    register("Histogram Distance", Arc::new(histogram::calculate_distance), Direction::Minimize);
+    register("Edge Compare", Arc::new(edge_compare::calculate_fitness), Direction::Minimize);
+    register("Genome Complexity", Arc::new(complexity::calculate_fitness), Direction::Minimize);
     fitness_registry
 }
 
@@ -33,6 +78,16 @@ struct EnvParams {
     num_triangles: usize,
     num_threads: usize,
     tournament_size: usize,
+    normalization: Normalization,
+    scalarization: Scalarization,
+    parent_selection: ParentSelection,
+    render_mode: RenderMode,
+    resolution_schedule: ResolutionSchedule,
+    genome_params: GenomeParams,
+    background_params: BackgroundParams,
+    paint_params: PaintParams,
+    optimizer_params: OptimizerParams,
+    local_search_params: LocalSearchParams,
 }
 
 impl EnvParams {
@@ -44,6 +99,16 @@ impl EnvParams {
             num_triangles: 250,
             num_threads: 16,
             tournament_size: 3,
+            normalization: Normalization::None,
+            scalarization: Scalarization::None,
+            parent_selection: ParentSelection::Tournament,
+            render_mode: RenderMode::Scanline,
+            resolution_schedule: ResolutionSchedule::default(),
+            genome_params: GenomeParams::default(),
+            background_params: BackgroundParams::default(),
+            paint_params: PaintParams::default(),
+            optimizer_params: OptimizerParams::default(),
+            local_search_params: LocalSearchParams::default(),
         }
     }
 }
@@ -52,21 +117,27 @@ struct EvoArtLab<'a> {
     running: bool,
     destination_folder: String,
     parameters: EnvParams,
-    fitness_functions: HashMap<String, FitnessFunction<'a>>,
+    fitness_functions: HashMap<String, Objective<'a>>,
     fitness_functions_checkbox: HashMap<String, bool>,
-    selected_functions: Vec<FitnessFunction<'a>>,
+    fitness_functions_weight: HashMap<String, f64>,
+    selected_functions: Vec<Objective<'a>>,
+    fitness_params: FitnessParams,
     time_elapsed: Option<Instant>,
     stop_condition: u16,
     environment: Option<Environment<'a>>,
     generation: usize,
     target_img: Option<image::RgbaImage>,
     img_path: String,
+    mask_path: String,
+    palette_text: String,
+    error: Option<String>,
 }
 
 impl<'a> Default for EvoArtLab<'a> {
     fn default() -> Self {
         let fitness_functions = define_fitness_functions();
         let fitness_functions_checkbox = fitness_functions.keys().map(|name| (name.clone(), false)).collect();
+        let fitness_functions_weight = fitness_functions.keys().map(|name| (name.clone(), 1.0)).collect();
         let env_params = EnvParams::new();
 
         Self {
@@ -75,13 +146,18 @@ impl<'a> Default for EvoArtLab<'a> {
             parameters: env_params,
             fitness_functions,
             fitness_functions_checkbox,
+            fitness_functions_weight,
             selected_functions: Vec::with_capacity(2),
+            fitness_params: FitnessParams::default(),
             time_elapsed: None,
             stop_condition: 30,
             environment: None,
             generation: 0,
             target_img: None,
             img_path: String::from("No image selected"),
+            mask_path: String::from("No mask selected"),
+            palette_text: String::from(color::DEFAULT_PALETTE),
+            error: None,
         }
     }
 }
@@ -100,21 +176,23 @@ impl<'a> eframe::App for EvoArtLab<'a> {
                         .iter()
                         .filter_map(|(name, &is_selected)| {
                             if is_selected {
-                                self.fitness_functions.get(name).cloned()
+                                self.fitness_functions.get(name).cloned().map(|mut objective| {
+                                    objective.weight = self.fitness_functions_weight[name];
+                                    objective
+                                })
                             } else {
                                 None
                             }
                         })
                         .collect();
 
-                    let final_img: Option<image::RgbaImage>;
-                    if self.target_img.is_none() {
-                        final_img = Some(image::open("test.jpg").unwrap().to_rgba8());
+                    let final_img: Option<image::RgbaImage> = if self.target_img.is_none() {
+                        Some(image::open("test.jpg").unwrap().to_rgba8())
                     } else {
-                        final_img = self.target_img.clone();
-                    }
+                        self.target_img.clone()
+                    };
 
-                    self.environment = Some(Environment::new(
+                    let environment = Environment::new(
                         self.parameters.pool_size,
                         self.parameters.scaling_factor,
                         self.parameters.crossover_probability,
@@ -123,13 +201,34 @@ impl<'a> eframe::App for EvoArtLab<'a> {
                         self.parameters.num_threads,
                         self.selected_functions.clone(),
                         self.parameters.tournament_size,
-                    ));
-                    self.environment.as_mut().unwrap().generate_initial_pool();
+                    );
+                    let env = match environment {
+                        Ok(env) => self.environment.insert(env),
+                        Err(error) => {
+                            self.error = Some(format!("Could not create the thread pool: {}", error));
+                            self.running = false;
+                            self.time_elapsed = None;
+                            return;
+                        }
+                    };
+                    self.error = None;
+                    env.fitness_params = self.fitness_params.clone();
+                    env.normalization = self.parameters.normalization;
+                    env.scalarization = self.parameters.scalarization;
+                    env.parent_selection = self.parameters.parent_selection;
+                    env.render_mode = self.parameters.render_mode;
+                    env.resolution_schedule = self.parameters.resolution_schedule;
+                    env.genome_params = self.parameters.genome_params;
+                    env.background_params = self.parameters.background_params;
+                    env.paint_params = self.parameters.paint_params;
+                    env.optimizer_params = self.parameters.optimizer_params;
+                    env.local_search_params = self.parameters.local_search_params;
+                    env.generate_initial_pool();
                     self.generation = 1;
                 }
 
                 if ui.button("Stop").clicked() {
-                    if self.running == false { return; }
+                    if !self.running { return; }
                     self.running = false;
                     self.time_elapsed = None;
                     self.environment = None;
@@ -157,38 +256,276 @@ impl<'a> eframe::App for EvoArtLab<'a> {
             ui.horizontal(|ui| {
                 ui.vertical(|ui| {
                     ui.heading("Parameters");
-                    ui.add(
-                        egui::DragValue::new(&mut self.parameters.pool_size)
-                           .prefix("Pool Size: ")
-                    );
-                    ui.add(
-                        egui::DragValue::new(&mut self.parameters.scaling_factor)
-                           .prefix("Scaling Factor: ")
-                    );
-                    ui.add(
-                        egui::DragValue::new(&mut self.parameters.crossover_probability)
-                        .prefix("Crossover Probability: ")
-                    );
-                    ui.add(
-                        egui::DragValue::new(&mut self.parameters.num_triangles)
-                        .prefix("Number of Triangles: ")
-                    );
-                    ui.add(
-                        egui::DragValue::new(&mut self.parameters.num_threads)
-                        .prefix("Number of Threads: ")
-                    );
-                    ui.add(
-                        egui::DragValue::new(&mut self.parameters.tournament_size)
-                        .prefix("Tournament Size: ")
-                    );
-
+                    egui::ScrollArea::vertical().id_salt("parameters").show(ui, |ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut self.parameters.pool_size)
+                               .prefix("Pool Size: ")
+                               .range(4..=usize::MAX)
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut self.parameters.scaling_factor)
+                               .prefix("Scaling Factor: ")
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut self.parameters.crossover_probability)
+                            .prefix("Crossover Probability: ")
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut self.parameters.num_triangles)
+                            .prefix("Number of Triangles: ")
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut self.parameters.num_threads)
+                            .range(1..=usize::MAX)
+                            .prefix("Number of Threads: ")
+                        );
+                        ui.label("Rasterizer:");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.parameters.render_mode, RenderMode::Reference, "Reference");
+                            ui.radio_value(&mut self.parameters.render_mode, RenderMode::Scanline, "Scanline");
+                            ui.radio_value(&mut self.parameters.render_mode, RenderMode::Tiled, "Tiled");
+                        });
+                        ui.label("Parent Selection:");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.parameters.parent_selection, ParentSelection::Tournament, "Tournament");
+                            ui.radio_value(&mut self.parameters.parent_selection, ParentSelection::Random, "Random");
+                            ui.radio_value(&mut self.parameters.parent_selection, ParentSelection::RankBased, "Rank-Based");
+                        });
+                        ui.add_enabled(
+                            self.parameters.parent_selection == ParentSelection::Tournament,
+                            egui::DragValue::new(&mut self.parameters.tournament_size)
+                            .range(1..=usize::MAX)
+                            .prefix("Tournament Size: ")
+                        );
+                        ui.label("Normalization:");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.parameters.normalization, Normalization::None, "None");
+                            ui.radio_value(&mut self.parameters.normalization, Normalization::MinMax, "Min-Max");
+                            ui.radio_value(&mut self.parameters.normalization, Normalization::ZScore, "Z-Score");
+                        });
+                        ui.label("Multiple Objectives:");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.parameters.scalarization, Scalarization::None, "NSGA-II");
+                            ui.radio_value(&mut self.parameters.scalarization, Scalarization::WeightedSum, "Weighted Sum");
+                            ui.radio_value(&mut self.parameters.scalarization, Scalarization::Tchebycheff, "Tchebycheff");
+                        });
+                        ui.collapsing("Optimizer", |ui| {
+                            let optimizer = &mut self.parameters.optimizer_params;
+                            egui::ComboBox::from_label("Algorithm")
+                                .selected_text(optimizer.kind.name())
+                                .show_ui(ui, |ui| {
+                                    for kind in OptimizerKind::ALL {
+                                        ui.selectable_value(&mut optimizer.kind, kind, kind.name());
+                                    }
+                                });
+                            let gaussian = !matches!(optimizer.kind, OptimizerKind::DifferentialEvolution | OptimizerKind::ParticleSwarm);
+                            ui.add_enabled_ui(gaussian && !matches!(optimizer.kind, OptimizerKind::CmaEs | OptimizerKind::Progressive), |ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut optimizer.mutation_rate)
+                                    .range(0.0..=1.0)
+                                    .speed(0.001)
+                                    .prefix("Mutation Rate: ")
+                                );
+                            });
+                            ui.add_enabled_ui(gaussian, |ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut optimizer.mutation_sigma)
+                                    .range(0.001..=1.0)
+                                    .speed(0.001)
+                                    .prefix("Mutation Sigma: ")
+                                );
+                            });
+                            ui.add_enabled_ui(optimizer.kind == OptimizerKind::HillClimbing, |ui| {
+                                ui.checkbox(&mut optimizer.one_fifth_rule, "1/5 Success Rule");
+                            });
+                            ui.add_enabled_ui(optimizer.kind == OptimizerKind::SimulatedAnnealing, |ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut optimizer.initial_temperature)
+                                    .range(0.0..=f64::MAX)
+                                    .speed(0.001)
+                                    .prefix("Initial Temperature: ")
+                                );
+                                ui.add(
+                                    egui::DragValue::new(&mut optimizer.cooling_rate)
+                                    .range(0.0..=1.0)
+                                    .speed(0.001)
+                                    .prefix("Cooling Rate: ")
+                                );
+                            });
+                            ui.add_enabled_ui(optimizer.kind == OptimizerKind::ParticleSwarm, |ui| {
+                                ui.label("Topology:");
+                                ui.horizontal(|ui| {
+                                    ui.radio_value(&mut optimizer.topology, SwarmTopology::Global, "Global Best");
+                                    ui.radio_value(&mut optimizer.topology, SwarmTopology::Ring, "Ring");
+                                });
+                                for (coefficient, prefix) in [
+                                    (&mut optimizer.inertia, "Inertia: "),
+                                    (&mut optimizer.cognitive, "Cognitive: "),
+                                    (&mut optimizer.social, "Social: "),
+                                ] {
+                                    ui.add(egui::DragValue::new(coefficient).range(0.0..=4.0).speed(0.01).prefix(prefix));
+                                }
+                            });
+                            ui.add_enabled_ui(optimizer.kind == OptimizerKind::Progressive, |ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut optimizer.generations_per_triangle)
+                                    .range(1..=usize::MAX)
+                                    .prefix("Generations per Triangle: ")
+                                );
+                            });
+                        });
+                        ui.collapsing("Local Search", |ui| {
+                            let local_search = &mut self.parameters.local_search_params;
+                            ui.checkbox(&mut local_search.enabled, "Enabled");
+                            ui.add_enabled_ui(local_search.enabled, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.radio_value(&mut local_search.target, LocalSearchTarget::Best, "Best");
+                                    ui.radio_value(&mut local_search.target, LocalSearchTarget::FirstFront, "First Front");
+                                });
+                                ui.add(
+                                    egui::DragValue::new(&mut local_search.interval)
+                                    .range(1..=usize::MAX)
+                                    .prefix("Every N Generations: ")
+                                );
+                                ui.add(
+                                    egui::DragValue::new(&mut local_search.budget)
+                                    .range(1..=usize::MAX)
+                                    .prefix("Evaluations per Search: ")
+                                );
+                                ui.add(
+                                    egui::DragValue::new(&mut local_search.step)
+                                    .range(0.001..=1.0)
+                                    .speed(0.001)
+                                    .prefix("Step: ")
+                                );
+                            });
+                        });
+                        ui.collapsing("Evaluation Resolution", |ui| {
+                            let schedule = &mut self.parameters.resolution_schedule;
+                            ui.add(
+                                egui::DragValue::new(&mut schedule.eval_scale)
+                                .range(0.01..=1.0)
+                                .speed(0.01)
+                                .prefix("Evaluation Scale: ")
+                            );
+                            ui.checkbox(&mut schedule.coarse_to_fine, "Coarse to Fine");
+                            ui.add_enabled(
+                                schedule.coarse_to_fine,
+                                egui::DragValue::new(&mut schedule.initial_scale)
+                                .range(0.01..=1.0)
+                                .speed(0.01)
+                                .prefix("Initial Scale: ")
+                            );
+                            ui.add_enabled(
+                                schedule.coarse_to_fine,
+                                egui::DragValue::new(&mut schedule.levels)
+                                .range(1..=usize::MAX)
+                                .prefix("Levels: ")
+                            );
+                            ui.add_enabled(
+                                schedule.coarse_to_fine,
+                                egui::DragValue::new(&mut schedule.generations_per_level)
+                                .range(1..=usize::MAX)
+                                .prefix("Generations per Level: ")
+                            );
+                        });
+                        ui.collapsing("Genome", |ui| {
+                            let genome = &mut self.parameters.genome_params;
+                            ui.checkbox(&mut genome.variable_length, "Variable Length");
+                            ui.add_enabled_ui(genome.variable_length, |ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut genome.min_triangles)
+                                    .range(1..=usize::MAX)
+                                    .prefix("Min Triangles: ")
+                                );
+                                ui.add(
+                                    egui::DragValue::new(&mut genome.max_triangles)
+                                    .range(1..=usize::MAX)
+                                    .prefix("Max Triangles: ")
+                                );
+                                for (probability, prefix) in [
+                                    (&mut genome.insert_probability, "Insert Probability: "),
+                                    (&mut genome.delete_probability, "Delete Probability: "),
+                                    (&mut genome.duplicate_probability, "Duplicate Probability: "),
+                                    (&mut genome.reorder_probability, "Reorder Probability: "),
+                                ] {
+                                    ui.add(egui::DragValue::new(probability).range(0.0..=1.0).speed(0.01).prefix(prefix));
+                                }
+                                ui.label("Alignment:");
+                                ui.horizontal(|ui| {
+                                    ui.radio_value(&mut genome.alignment, Alignment::Positional, "Positional");
+                                    ui.radio_value(&mut genome.alignment, Alignment::Proportional, "Proportional");
+                                });
+                            });
+                            ui.add(
+                                egui::DragValue::new(&mut genome.swap_probability)
+                                .range(0.0..=1.0)
+                                .speed(0.001)
+                                .prefix("Layer Swap Probability: ")
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut genome.shift_probability)
+                                .range(0.0..=1.0)
+                                .speed(0.01)
+                                .prefix("Layer Shift Probability: ")
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut genome.max_shift)
+                                .prefix("Max Layer Shift: ")
+                            );
+                            ui.label("Crossover:");
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut genome.crossover, LayerCrossover::Positional, "Positional");
+                                ui.radio_value(&mut genome.crossover, LayerCrossover::OrderPreserving, "Order-Preserving");
+                            });
+                        });
+                        ui.collapsing("Background", |ui| {
+                            let background = &mut self.parameters.background_params;
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut background.mode, BackgroundMode::Transparent, "Transparent");
+                                ui.radio_value(&mut background.mode, BackgroundMode::Fixed, "Fixed");
+                                ui.radio_value(&mut background.mode, BackgroundMode::Evolved, "Evolved");
+                            });
+                            ui.add_enabled_ui(background.mode == BackgroundMode::Fixed, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Color:");
+                                    ui.color_edit_button_srgb(&mut background.color);
+                                });
+                            });
+                            ui.add_enabled_ui(background.mode != BackgroundMode::Transparent, |ui| {
+                                ui.checkbox(&mut background.composite_measures, "Composite for Measures");
+                                ui.checkbox(&mut background.composite_export, "Composite on Export");
+                            });
+                        });
+                        ui.collapsing("Paint", |ui| {
+                            let paint = &mut self.parameters.paint_params;
+                            ui.add(egui::DragValue::new(&mut paint.min_alpha).prefix("Min Alpha: "));
+                            ui.add(egui::DragValue::new(&mut paint.max_alpha).prefix("Max Alpha: "));
+                            egui::ComboBox::from_label("Blend Mode")
+                                .selected_text(format!("{:?}", paint.blend_mode))
+                                .show_ui(ui, |ui| {
+                                    for mode in BlendMode::ALL {
+                                        ui.selectable_value(&mut paint.blend_mode, mode, format!("{:?}", mode));
+                                    }
+                                });
+                            ui.checkbox(&mut paint.evolve_blend_mode, "Evolve per Triangle");
+                            ui.add_enabled_ui(paint.evolve_blend_mode, |ui| {
+                                ui.add(
+                                    egui::DragValue::new(&mut paint.blend_mutation_probability)
+                                    .range(0.0..=1.0)
+                                    .speed(0.001)
+                                    .prefix("Blend Mutation Probability: ")
+                                );
+                            });
+                        });
+                    });
                 });
 
                 ui.vertical(|ui| {
                     ui.horizontal(|ui| {
                         ui.vertical(|ui| {
                             ui.heading("Stop Condition");
-                            ui.add(egui::DragValue::new(&mut self.stop_condition).prefix(format!("Time Elapsed in Seconds:")));
+                            ui.add(egui::DragValue::new(&mut self.stop_condition).prefix("Time Elapsed in Seconds:"));
                         });
                     });
 
@@ -196,13 +533,182 @@ impl<'a> eframe::App for EvoArtLab<'a> {
                         ui.vertical(|ui| {
                             ui.heading("Fitness Functions");
                             egui::ScrollArea::vertical().show(ui, |ui| {
+                                let weighted = self.parameters.scalarization != Scalarization::None;
                                 for (key, toggled) in self.fitness_functions_checkbox.iter_mut() {
-                                    ui.checkbox(toggled, key);
+                                    ui.horizontal(|ui| {
+                                        ui.checkbox(toggled, key);
+                                        if let Some(weight) = self.fitness_functions_weight.get_mut(key) {
+                                            ui.add_enabled(weighted, egui::Slider::new(weight, 0.0..=1.0));
+                                        }
+                                    });
                                 }
                             });
                         });
                     });
                 });
+
+                ui.vertical(|ui| {
+                    ui.heading("Fitness Settings");
+                    egui::ScrollArea::vertical().id_salt("fitness_settings").show(ui, |ui| {
+                        ui.collapsing("Delta E 2000", |ui| {
+                            let params = &mut self.fitness_params.delta_e;
+                            ui.checkbox(&mut params.ignore_alpha, "Ignore Alpha");
+                            ui.horizontal(|ui| {
+                                if ui.button("Choose Mask").clicked() {
+                                    if let Some(img) = FileDialog::new().pick_file() {
+                                        self.mask_path = img.to_str().unwrap().to_string();
+                                        params.mask = Some(image::open(img).unwrap().to_luma8());
+                                    }
+                                }
+                                if ui.button("Clear Mask").clicked() {
+                                    self.mask_path = "No mask selected".to_string();
+                                    params.mask = None;
+                                }
+                            });
+                            ui.label(format!("Mask: {}", self.mask_path));
+                        });
+
+                        ui.collapsing("Compression Complexity", |ui| {
+                            let params = &mut self.fitness_params.compression;
+                            ui.add(
+                                egui::DragValue::new(&mut params.jpeg_quality)
+                                .range(1..=100)
+                                .prefix("JPEG Quality: ")
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut params.target_image_complexity)
+                                .speed(0.01)
+                                .prefix("Target Image Complexity: ")
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut params.target_complexity_ratio)
+                                .speed(0.01)
+                                .prefix("Target Complexity Ratio: ")
+                            );
+                        });
+
+                        ui.collapsing("Fractal Dimension", |ui| {
+                            let params = &mut self.fitness_params.fractal;
+                            ui.add(
+                                egui::DragValue::new(&mut params.target_dimension)
+                                .range(0.0..=2.0)
+                                .speed(0.01)
+                                .prefix("Target Dimension: ")
+                            );
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut params.source, FractalSource::Edges, "Edges");
+                                ui.radio_value(&mut params.source, FractalSource::Luminance, "Luminance");
+                            });
+                            ui.add(
+                                egui::DragValue::new(&mut params.edge_threshold)
+                                .prefix("Edge Threshold: ")
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut params.luminance_threshold)
+                                .range(0.0..=255.0)
+                                .prefix("Luminance Threshold: ")
+                            );
+                        });
+
+                        ui.collapsing("Color Harmony", |ui| {
+                            let params = &mut self.fitness_params.color;
+                            egui::ComboBox::from_label("Template")
+                                .selected_text(format!("{:?}", params.harmonic_template))
+                                .show_ui(ui, |ui| {
+                                    for template in HarmonicTemplate::ALL {
+                                        ui.selectable_value(&mut params.harmonic_template, template, format!("{:?}", template));
+                                    }
+                                });
+                        });
+
+                        ui.collapsing("Palette Distance", |ui| {
+                            ui.label("Palette (hex colors):");
+                            if ui.text_edit_singleline(&mut self.palette_text).changed() {
+                                self.fitness_params.color.palette = color::parse_palette(&self.palette_text);
+                            }
+                            ui.label(format!("{} colors parsed", self.fitness_params.color.palette.len()));
+                        });
+
+                        ui.collapsing("Spectral Slope", |ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut self.fitness_params.spectral.target_slope)
+                                .speed(0.01)
+                                .prefix("Target Slope: ")
+                            );
+                        });
+
+                        ui.collapsing("Bell Curve", |ui| {
+                            let params = &mut self.fitness_params.bell_curve;
+                            ui.add(
+                                egui::DragValue::new(&mut params.detection_threshold)
+                                .range(0.01..=f64::MAX)
+                                .speed(0.1)
+                                .prefix("Detection Threshold: ")
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut params.bins_per_std_dev)
+                                .range(1.0..=1000.0)
+                                .prefix("Bins per Std Dev: ")
+                            );
+                            ui.checkbox(&mut params.fit_distribution, "Fit Distribution");
+                            ui.add_enabled(
+                                !params.fit_distribution,
+                                egui::DragValue::new(&mut params.mean)
+                                .speed(0.01)
+                                .prefix("Mean: ")
+                            );
+                            ui.add_enabled(
+                                !params.fit_distribution,
+                                egui::DragValue::new(&mut params.std_dev)
+                                .range(0.01..=f64::MAX)
+                                .speed(0.01)
+                                .prefix("Std Deviation: ")
+                            );
+                        });
+
+                        ui.collapsing("Histogram", |ui| {
+                            let params = &mut self.fitness_params.histogram;
+                            ui.add(
+                                egui::DragValue::new(&mut params.bins_per_channel)
+                                .range(1..=64)
+                                .prefix("Bins per Channel: ")
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut params.spread_percentile)
+                                .range(0.0..=0.5)
+                                .speed(0.001)
+                                .prefix("Spread Percentile: ")
+                            );
+                        });
+
+                        ui.collapsing("Edge Compare", |ui| {
+                            let params = &mut self.fitness_params.edge_compare;
+                            ui.horizontal(|ui| {
+                                ui.radio_value(&mut params.mode, EdgeMode::Gradient, "Sobel Gradient");
+                                ui.radio_value(&mut params.mode, EdgeMode::Canny, "Canny Edges");
+                            });
+                            ui.add_enabled(
+                                params.mode == EdgeMode::Gradient,
+                                egui::DragValue::new(&mut params.orientation_weight)
+                                .range(0.0..=f64::MAX)
+                                .speed(0.01)
+                                .prefix("Orientation Weight: ")
+                            );
+                            ui.add_enabled(
+                                params.mode == EdgeMode::Canny,
+                                egui::DragValue::new(&mut params.low_threshold)
+                                .range(0.0..=f64::MAX)
+                                .prefix("Low Threshold: ")
+                            );
+                            ui.add_enabled(
+                                params.mode == EdgeMode::Canny,
+                                egui::DragValue::new(&mut params.high_threshold)
+                                .range(0.0..=f64::MAX)
+                                .prefix("High Threshold: ")
+                            );
+                        });
+                    });
+                });
             });
         });
 
@@ -219,25 +725,28 @@ impl<'a> eframe::App for EvoArtLab<'a> {
                     ui.label("Time Elapsed: N/A");
                 }
 
-                ui.horizontal(|ui| {
-                    let mean_fitness;
-                    let std_deviation;
-
-                    if let Some(env) = &self.environment {
-                        let mean = env.fitness_mean();
-                        let std = env.fitness_std_dev();
-                        let mean_str = mean.iter().map(|f| format!("{:.1}", f)).collect::<Vec<String>>().join(", ");
-                        let std_str = std.iter().map(|f| format!("{:.1}", f)).collect::<Vec<String>>().join(", ");
-                        mean_fitness = format!("Mean Fitness: ({})", mean_str);
-                        std_deviation = format!("Std Deviation: ({})", std_str);
-                    } else {
-                        mean_fitness = "Mean Fitness: N/A".to_string();
-                        std_deviation = "Std Deviation: N/A".to_string();
+                if let Some(env) = &self.environment {
+                    let mean = env.fitness_mean();
+                    let std = env.fitness_std_dev();
+                    for (i, objective) in env.objectives.iter().enumerate() {
+                        let direction = match objective.direction {
+                            Direction::Minimize => "min",
+                            Direction::Maximize => "max",
+                        };
+                        ui.label(format!("{} ({}): Mean {:.3}, Std Deviation {:.3}", objective.name, direction, mean[i], std[i]));
                     }
-
-                    ui.label(mean_fitness);
-                    ui.label(std_deviation);
-                });
+                    if let Some(base) = &env.base_canvas {
+                        ui.label(format!("Frozen Triangles: {} of {}", base.triangles, env.num_triangles));
+                    }
+                    for warning in env.warnings() {
+                        ui.colored_label(egui::Color32::RED, warning);
+                    }
+                } else {
+                    ui.label("Mean Fitness: N/A");
+                    if let Some(error) = &self.error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                }
             });
         });
 
@@ -250,16 +759,23 @@ impl<'a> eframe::App for EvoArtLab<'a> {
                     // get first front
                     let front = env.get_first_front();
                     // save all vectors in the first front if at least one fitness was selected
-                    for (_, vector) in front.iter().enumerate() {
-                        if vector.fitness.len() != 0 {
-                            let img = draw::draw_vector(&vector, env.target_width, env.target_height);
+                    for vector in front.iter() {
+                        if !vector.fitness.is_empty() {
+                            let img = env.thread_pool.install(|| {
+                                draw::draw_vector(
+                                    vector,
+                                    env.target_width,
+                                    env.target_height,
+                                    env.background(vector, env.background_params.composite_export),
+                                    env.render_mode
+                                )
+                            });
                             let fitnesses = vector.fitness.iter().map(|f| f.to_string()).collect::<Vec<String>>().join("_");
-                            let file_name;
-                            if self.destination_folder != "No folder selected" {
-                                file_name = format!("{}/front_{}.png", self.destination_folder, fitnesses);
+                            let file_name = if self.destination_folder != "No folder selected" {
+                                format!("{}/front_{}.png", self.destination_folder, fitnesses)
                             } else {
-                                file_name = format!("front_{}.png", fitnesses);
-                            }
+                                format!("front_{}.png", fitnesses)
+                            };
                             img.save(file_name).unwrap();
                         }
                     }
@@ -283,8 +799,8 @@ fn main() {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder {
             title: Some("EvoArtLab".to_string()),
-            min_inner_size: Some(egui::vec2(420.0, 350.0)),
-            max_inner_size: Some(egui::vec2(420.0, 350.0)),
+            min_inner_size: Some(egui::vec2(900.0, 520.0)),
+            max_inner_size: Some(egui::vec2(900.0, 520.0)),
             ..Default::default()
         },
         centered: true,
@@ -0,0 +1,140 @@
+use crate::vector::{Environment, Vector};
+
+pub mod differential_evolution;
+pub mod hill_climbing;
+pub mod simulated_annealing;
+pub mod genetic;
+pub mod cma_es;
+pub mod particle_swarm;
+pub mod progressive;
+
+// a search strategy over the pool of the environment. every optimiser works on the same genome
+// and fitness functions, evaluates through `calculate_fitness_for_population` and leaves the pool
+// ranked from best to worst so the front and statistics are reported the same way. the
+// environment is shared with the evaluation threads, hence the bounds
+pub trait Optimizer: Send + Sync {
+    fn step(&mut self, env: &mut Environment);
+
+    // called after the local search with each vector it improved and the refined copy now in the
+    // pool. optimisers that rebuild the pool from their own state take the refinements over here
+    fn accept_improved(&mut self, _env: &Environment, _improved: &[(Vector, Vector)]) {}
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum OptimizerKind {
+    DifferentialEvolution,
+    // (1+1)-ES on every vector of the pool, the classic hill climber
+    HillClimbing,
+    SimulatedAnnealing,
+    Genetic,
+    // separable CMA-ES, as the full covariance of thousands of genes is too large to adapt
+    CmaEs,
+    ParticleSwarm,
+    // one triangle at a time over the frozen ones, see `progressive`
+    Progressive,
+}
+
+impl OptimizerKind {
+    pub const ALL: [OptimizerKind; 7] = [
+        OptimizerKind::DifferentialEvolution,
+        OptimizerKind::HillClimbing,
+        OptimizerKind::SimulatedAnnealing,
+        OptimizerKind::Genetic,
+        OptimizerKind::CmaEs,
+        OptimizerKind::ParticleSwarm,
+        OptimizerKind::Progressive,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OptimizerKind::DifferentialEvolution => "Differential Evolution",
+            OptimizerKind::HillClimbing => "(1+1)-ES / Hill Climbing",
+            OptimizerKind::SimulatedAnnealing => "Simulated Annealing",
+            OptimizerKind::Genetic => "Genetic Algorithm",
+            OptimizerKind::CmaEs => "CMA-ES",
+            OptimizerKind::ParticleSwarm => "Particle Swarm",
+            OptimizerKind::Progressive => "Progressive Construction",
+        }
+    }
+}
+
+// neighbourhood a particle takes its leader from when the swarm is scalarised
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum SwarmTopology {
+    Global,
+    // the particle and its two neighbours by index
+    Ring,
+}
+
+// the differential evolution settings stay on the environment, the rest are shared by the
+// optimisers that use gaussian mutation
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct OptimizerParams {
+    pub kind: OptimizerKind,
+    // probability of each triangle being perturbed
+    pub mutation_rate: f64,
+    // standard deviation of the perturbation as a share of the coordinate or channel range,
+    // also the initial step size of CMA-ES
+    pub mutation_sigma: f64,
+    // adapts the step size of hill climbing with the 1/5 success rule
+    pub one_fifth_rule: bool,
+    pub initial_temperature: f64,
+    pub cooling_rate: f64,
+    pub topology: SwarmTopology,
+    pub inertia: f64,
+    pub cognitive: f64,
+    pub social: f64,
+    pub generations_per_triangle: usize,
+}
+
+impl Default for OptimizerParams {
+    fn default() -> Self {
+        Self {
+            kind: OptimizerKind::DifferentialEvolution,
+            mutation_rate: 0.02,
+            mutation_sigma: 0.1,
+            one_fifth_rule: true,
+            initial_temperature: 0.01,
+            cooling_rate: 0.99,
+            // the constriction coefficients of Clerc and Kennedy
+            topology: SwarmTopology::Global,
+            inertia: 0.7298,
+            cognitive: 1.49618,
+            social: 1.49618,
+            generations_per_triangle: 20,
+        }
+    }
+}
+
+impl OptimizerParams {
+    pub fn create(&self) -> Box<dyn Optimizer> {
+        match self.kind {
+            OptimizerKind::DifferentialEvolution => Box::new(differential_evolution::DifferentialEvolution),
+            OptimizerKind::HillClimbing => Box::new(hill_climbing::HillClimbing::new(self)),
+            OptimizerKind::SimulatedAnnealing => Box::new(simulated_annealing::SimulatedAnnealing::new(self)),
+            OptimizerKind::Genetic => Box::new(genetic::Genetic),
+            OptimizerKind::CmaEs => Box::new(cma_es::CmaEs::default()),
+            OptimizerKind::ParticleSwarm => Box::new(particle_swarm::ParticleSwarm::default()),
+            OptimizerKind::Progressive => Box::new(progressive::Progressive::default()),
+        }
+    }
+}
+
+// gaussian mutation and the genome mutations of every vector of the pool
+fn mutate_pool(env: &mut Environment, sigma: f64) {
+    let params = env.optimizer_params;
+    let mut pool = std::mem::take(&mut env.pool);
+    for vector in pool.iter_mut() {
+        vector.gaussian_mutation(params.mutation_rate, sigma, env.target_width, env.target_height, &env.paint_params);
+        env.mutate_genome(vector);
+    }
+    env.pool = pool;
+}
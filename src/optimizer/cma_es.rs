@@ -0,0 +1,127 @@
+use rand::Rng;
+use statrs::distribution::Normal;
+
+use crate::optimizer::Optimizer;
+use crate::vector::{Environment, Vector};
+
+// separable CMA-ES (Ros and Hansen, 2008) on the genes of `Vector::to_genes`. only the diagonal
+// of the covariance is adapted, which keeps every update linear in the number of genes. the pool
+// holds the samples of the current generation, the length of the genome and the blend modes are
+// taken from the best vector when the search starts and stay fixed
+#[derive(Default)]
+pub struct CmaEs {
+    state: Option<State>,
+}
+
+struct State {
+    template: Vector,
+    mean: Vec<f64>,
+    sigma: f64,
+    variances: Vec<f64>,
+    sigma_path: Vec<f64>,
+    covariance_path: Vec<f64>,
+    generation: usize,
+}
+
+impl Optimizer for CmaEs {
+    fn step(&mut self, env: &mut Environment) {
+        let (width, height) = (env.target_width, env.target_height);
+        let state = self.state.get_or_insert_with(|| {
+            // the pool is ranked, so the search starts around its best vector
+            let template = env.pool[0].clone();
+            let mean = template.to_genes(width, height);
+            let n = mean.len();
+            State {
+                template,
+                mean,
+                sigma: env.optimizer_params.mutation_sigma,
+                variances: vec![1.0; n],
+                sigma_path: vec![0.0; n],
+                covariance_path: vec![0.0; n],
+                generation: 0,
+            }
+        });
+
+        let n = state.mean.len() as f64;
+        let lambda = env.pool_size;
+        let mu = (lambda / 2).max(1);
+        let weights: Vec<f64> = (0..mu).map(|i| (mu as f64 + 0.5).ln() - (i as f64 + 1.0).ln()).collect();
+        let weight_sum: f64 = weights.iter().sum();
+        let weights: Vec<f64> = weights.iter().map(|w| w / weight_sum).collect();
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let d_sigma = 1.0 + 2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma;
+        let c_c = (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n);
+        // the separable variant may learn (n + 2) / 3 times faster than the full one
+        let c_1 = 2.0 / ((n + 1.3).powi(2) + mu_eff) * (n + 2.0) / 3.0;
+        let c_mu = (2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((n + 2.0).powi(2) + mu_eff) * (n + 2.0) / 3.0).min(1.0 - c_1);
+        let expected_norm = n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n));
+
+        // x = mean + sigma * sqrt(C) * z
+        let mut rng = rand::thread_rng();
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        env.pool = (0..lambda)
+            .map(|_| {
+                let genes: Vec<f64> = state
+                    .mean
+                    .iter()
+                    .zip(state.variances.iter())
+                    .map(|(m, v)| m + state.sigma * v.sqrt() * rng.sample(normal))
+                    .collect();
+                Vector::from_genes(&genes, &state.template, width, height, &env.paint_params)
+            })
+            .collect();
+        env.calculate_fitness_for_population();
+        env.rank_pool();
+
+        // the update uses the rounded and clamped genes that were actually evaluated
+        let steps: Vec<Vec<f64>> = env.pool[..mu]
+            .iter()
+            .map(|vector| {
+                vector
+                    .to_genes(width, height)
+                    .iter()
+                    .zip(state.mean.iter())
+                    .map(|(x, m)| (x - m) / state.sigma)
+                    .collect()
+            })
+            .collect();
+        let mean_step: Vec<f64> = (0..state.mean.len())
+            .map(|i| steps.iter().zip(weights.iter()).map(|(y, w)| w * y[i]).sum())
+            .collect();
+        for (m, y) in state.mean.iter_mut().zip(mean_step.iter()) {
+            *m += state.sigma * y;
+        }
+
+        let sigma_factor = (c_sigma * (2.0 - c_sigma) * mu_eff).sqrt();
+        for ((p, y), v) in state.sigma_path.iter_mut().zip(mean_step.iter()).zip(state.variances.iter()) {
+            *p = (1.0 - c_sigma) * *p + sigma_factor * y / v.sqrt();
+        }
+        state.generation += 1;
+        let sigma_path_norm = state.sigma_path.iter().map(|p| p * p).sum::<f64>().sqrt();
+        let h_sigma = sigma_path_norm / (1.0 - (1.0 - c_sigma).powi(2 * state.generation as i32)).sqrt()
+            < (1.4 + 2.0 / (n + 1.0)) * expected_norm;
+        let h_sigma = if h_sigma { 1.0 } else { 0.0 };
+
+        let covariance_factor = (c_c * (2.0 - c_c) * mu_eff).sqrt();
+        for (i, v) in state.variances.iter_mut().enumerate() {
+            let p = &mut state.covariance_path[i];
+            *p = (1.0 - c_c) * *p + h_sigma * covariance_factor * mean_step[i];
+            let rank_mu: f64 = steps.iter().zip(weights.iter()).map(|(y, w)| w * y[i] * y[i]).sum();
+            *v = (1.0 - c_1 - c_mu) * *v + c_1 * (*p * *p + (1.0 - h_sigma) * c_c * (2.0 - c_c) * *v) + c_mu * rank_mu;
+        }
+        state.sigma *= ((c_sigma / d_sigma) * (sigma_path_norm / expected_norm - 1.0)).exp();
+    }
+
+    // when a refined sample is the best of the generation the mean moves to it, so the next
+    // samples are drawn around the refinement instead of discarding it
+    fn accept_improved(&mut self, env: &Environment, improved: &[(Vector, Vector)]) {
+        let Some(state) = self.state.as_mut() else { return };
+        let (width, height) = (env.target_width, env.target_height);
+        let best = env.pool[0].to_genes(width, height);
+        if improved.iter().any(|(_, refined)| refined.to_genes(width, height) == best) {
+            state.mean = best;
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::optimizer::Optimizer;
+use crate::vector::{Environment, Vector};
+
+pub struct DifferentialEvolution;
+
+impl Optimizer for DifferentialEvolution {
+    fn step(&mut self, env: &mut Environment) {
+        let old_pool = env.pool.clone();
+
+        // mutate the pool via formula
+        // xr1 + scaling_factor * (xr2 - xr3)
+        // parents always come from the current generation, never from the new trial vectors
+        let mut trial_pool = Vec::with_capacity(env.pool_size);
+        for i in 0..env.pool_size {
+            let [r1, r2, r3] = env.select_parents(i);
+
+            let mutant_vector = Vector::generate_mutant_vector(&env.pool[r1], &env.pool[r2], &env.pool[r3], env);
+
+            let mut trial_vector = Vector::crossover(
+                &mutant_vector,
+                &env.pool[i],
+                env.crossover_probability,
+                &env.genome_params,
+                env.num_objectives
+            );
+            env.mutate_genome(&mut trial_vector);
+            trial_pool.push(trial_vector);
+        }
+        env.pool = trial_pool;
+
+        // calculate the fitness of the current pool
+        env.calculate_fitness_for_population();
+        env.select_survivors(old_pool);
+    }
+}
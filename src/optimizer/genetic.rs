@@ -0,0 +1,24 @@
+use crate::optimizer::{mutate_pool, Optimizer};
+use crate::vector::{Environment, Vector};
+
+// generational GA with uniform crossover of two selected parents, gaussian mutation of the
+// children and (mu + lambda) survival, so the best vectors are never lost
+pub struct Genetic;
+
+impl Optimizer for Genetic {
+    fn step(&mut self, env: &mut Environment) {
+        let old_pool = env.pool.clone();
+
+        let children = (0..env.pool_size)
+            .map(|i| {
+                let [p1, p2, _] = env.select_parents(i);
+                Vector::crossover(&env.pool[p1], &env.pool[p2], 0.5, &env.genome_params, env.num_objectives)
+            })
+            .collect();
+        env.pool = children;
+        mutate_pool(env, env.optimizer_params.mutation_sigma);
+
+        env.calculate_fitness_for_population();
+        env.select_best_of_both(old_pool);
+    }
+}
@@ -0,0 +1,30 @@
+use crate::optimizer::{mutate_pool, Optimizer, OptimizerParams};
+use crate::vector::Environment;
+
+// every vector of the pool is an independent (1+1)-ES: it is mutated and the child replaces it
+// unless it is worse, so moves that change nothing are accepted as well
+pub struct HillClimbing {
+    sigma: f64,
+}
+
+impl HillClimbing {
+    pub fn new(params: &OptimizerParams) -> Self {
+        Self { sigma: params.mutation_sigma }
+    }
+}
+
+impl Optimizer for HillClimbing {
+    fn step(&mut self, env: &mut Environment) {
+        let old_pool = env.pool.clone();
+        mutate_pool(env, self.sigma);
+        env.calculate_fitness_for_population();
+        let improved = env.select_survivors(old_pool);
+
+        // 1/5 success rule over the whole pool, with the step size kept inside a sane range
+        if env.optimizer_params.one_fifth_rule && !improved.is_empty() {
+            let success_rate = improved.iter().filter(|&&improved| improved).count() as f64 / improved.len() as f64;
+            let factor = if success_rate > 0.2 { 1.0 / 0.85 } else if success_rate < 0.2 { 0.85 } else { 1.0 };
+            self.sigma = (self.sigma * factor).clamp(0.001, 1.0);
+        }
+    }
+}
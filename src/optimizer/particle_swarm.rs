@@ -0,0 +1,143 @@
+use rand::Rng;
+
+use crate::optimizer::{Optimizer, SwarmTopology};
+use crate::vector::{dominates, Environment, Vector};
+
+// largest change of a gene in one step, as a share of its range
+const MAX_VELOCITY: f64 = 0.2;
+
+// particle swarm over the genes of `Vector::to_genes`, with the positions kept as vectors so they
+// are evaluated like any pool. the pool reports the personal bests. with several objectives and
+// no scalarisation the leaders are drawn from an archive of the non-dominated positions found,
+// bounded to the pool size by crowding distance
+#[derive(Default)]
+pub struct ParticleSwarm {
+    state: Option<State>,
+}
+
+struct Particle {
+    position: Vector,
+    velocity: Vec<f64>,
+    best: Vector,
+}
+
+struct State {
+    particles: Vec<Particle>,
+    archive: Vec<Vector>,
+    // resolution the stored fitness values were computed at
+    resolution: (u32, u32),
+}
+
+impl Optimizer for ParticleSwarm {
+    fn step(&mut self, env: &mut Environment) {
+        let mut rng = rand::thread_rng();
+        let (width, height) = (env.target_width, env.target_height);
+        let state = self.state.get_or_insert_with(|| State {
+            particles: env
+                .pool
+                .iter()
+                .map(|vector| Particle {
+                    position: vector.clone(),
+                    velocity: vec![0.0; vector.to_genes(width, height).len()],
+                    best: vector.clone(),
+                })
+                .collect(),
+            archive: Vec::new(),
+            resolution: (env.eval_width, env.eval_height),
+        });
+
+        // the personal bests are evaluated again after a change of resolution
+        if state.resolution != (env.eval_width, env.eval_height) {
+            env.pool = state.particles.iter().map(|particle| particle.best.clone()).collect();
+            env.calculate_fitness_for_population();
+            for (particle, best) in state.particles.iter_mut().zip(env.pool.drain(..)) {
+                particle.best = best;
+            }
+            state.archive.clear();
+            state.resolution = (env.eval_width, env.eval_height);
+        }
+        let mut bests: Vec<Vector> = state.particles.iter().map(|particle| particle.best.clone()).collect();
+        env.calculate_scores(&mut bests);
+        env.calculate_scores(&mut state.archive);
+
+        let params = env.optimizer_params;
+        let multi_objective = !env.is_scalarized();
+        let count = state.particles.len();
+        env.pool = Vec::with_capacity(count);
+        for (i, particle) in state.particles.iter_mut().enumerate() {
+            let leader = if multi_objective && !state.archive.is_empty() {
+                &state.archive[rng.gen_range(0..state.archive.len())]
+            } else {
+                let neighbours = match params.topology {
+                    SwarmTopology::Global => (0..count).collect(),
+                    SwarmTopology::Ring => vec![(i + count - 1) % count, i, (i + 1) % count],
+                };
+                let best = neighbours
+                    .into_iter()
+                    .min_by(|&a, &b| env.scalarize(&bests[a]).total_cmp(&env.scalarize(&bests[b])))
+                    .unwrap();
+                &bests[best]
+            };
+
+            // v = w * v + c1 * r1 * (best - x) + c2 * r2 * (leader - x)
+            let position = particle.position.to_genes(width, height);
+            let best = particle.best.to_genes(width, height);
+            let leader = leader.to_genes(width, height);
+            let genes: Vec<f64> = particle
+                .velocity
+                .iter_mut()
+                .zip(position.iter().zip(best.iter().zip(leader.iter())))
+                .map(|(v, (x, (b, l)))| {
+                    *v = params.inertia * *v
+                        + params.cognitive * rng.gen::<f64>() * (b - x)
+                        + params.social * rng.gen::<f64>() * (l - x);
+                    *v = v.clamp(-MAX_VELOCITY, MAX_VELOCITY);
+                    x + *v
+                })
+                .collect();
+            env.pool.push(Vector::from_genes(&genes, &particle.position, width, height, &env.paint_params));
+        }
+        env.calculate_fitness_for_population();
+        env.calculate_scores(&mut bests);
+
+        for ((particle, position), best) in state.particles.iter_mut().zip(env.pool.iter()).zip(bests.iter()) {
+            let improved = if multi_objective {
+                dominates(position, best) || (!dominates(best, position) && rng.gen_bool(0.5))
+            } else {
+                env.scalarize(position) <= env.scalarize(best)
+            };
+            particle.position = position.clone();
+            particle.best = if improved { position.clone() } else { best.clone() };
+        }
+
+        if multi_objective {
+            let mut candidates = std::mem::take(&mut state.archive);
+            candidates.extend(env.pool.iter().cloned());
+            env.calculate_scores(&mut candidates);
+            let mut archive = env.non_dominated_sort(&candidates).into_iter().next().unwrap_or_default();
+            if archive.len() > env.pool_size {
+                env.calculate_crowding_distance(&mut archive);
+                archive.sort_by(|a, b| b.crowding_distance.total_cmp(&a.crowding_distance));
+                archive.truncate(env.pool_size);
+            }
+            state.archive = archive;
+        }
+
+        let mut pool: Vec<Vector> = state.particles.iter().map(|particle| particle.best.clone()).collect();
+        env.calculate_scores(&mut pool);
+        env.pool = pool;
+        env.rank_pool();
+    }
+
+    // a refined personal best replaces the one it came from
+    fn accept_improved(&mut self, env: &Environment, improved: &[(Vector, Vector)]) {
+        let Some(state) = self.state.as_mut() else { return };
+        let (width, height) = (env.target_width, env.target_height);
+        for (original, refined) in improved {
+            let genes = original.to_genes(width, height);
+            if let Some(particle) = state.particles.iter_mut().find(|particle| particle.best.to_genes(width, height) == genes) {
+                particle.best = refined.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use image::RgbaImage;
+use std::sync::Arc;
+
+use crate::draw::{self, BaseCanvas};
+use crate::optimizer::Optimizer;
+use crate::vector::{dominates, Environment, Triangle, Vector};
+
+// builds the image one triangle at a time. every vector of the pool is the frozen triangles plus
+// one candidate, hill climbed for `generations_per_triangle` generations over the base canvas
+// that holds the frozen ones, so an evaluation only draws the candidate. the best candidate is
+// frozen if it improves the image, otherwise new candidates are tried, until `num_triangles`
+#[derive(Default)]
+pub struct Progressive {
+    state: Option<State>,
+}
+
+struct State {
+    // the frozen triangles, evaluated at the current resolution
+    frozen: Vector,
+    generations: usize,
+}
+
+impl Optimizer for Progressive {
+    fn step(&mut self, env: &mut Environment) {
+        let initial = self.state.is_none();
+        let state = self.state.get_or_insert_with(|| {
+            let mut frozen = env.pool[0].clone();
+            frozen.triangles.clear();
+            State { frozen, generations: 0 }
+        });
+
+        // the canvas is drawn again from scratch at the start and after a change of resolution
+        let resolution = (env.eval_width, env.eval_height);
+        if env.base_canvas.as_ref().is_none_or(|base| base.image.dimensions() != resolution) {
+            let mut image = RgbaImage::new(resolution.0, resolution.1);
+            let scale = (resolution.0 as f32 / env.target_width as f32, resolution.1 as f32 / env.target_height as f32);
+            let background = env.background(&state.frozen, env.background_params.composite_measures);
+            env.thread_pool.install(|| draw::draw_vector_into(&mut image, &state.frozen, background, scale, env.render_mode));
+            env.base_canvas = Some(BaseCanvas { image, triangles: state.frozen.triangles.len() });
+            state.frozen = evaluate(env, &state.frozen);
+            if initial {
+                start_candidates(env, &state.frozen);
+                return;
+            }
+        }
+        if state.frozen.triangles.len() >= env.num_triangles {
+            return;
+        }
+
+        // one (1+1)-ES generation on the candidate of every vector
+        let old_pool = env.pool.clone();
+        let mut rng = rand::thread_rng();
+        let (width, height, sigma) = (env.target_width, env.target_height, env.optimizer_params.mutation_sigma);
+        for vector in env.pool.iter_mut() {
+            if let Some(candidate) = vector.triangles.last_mut() {
+                candidate.gaussian_mutation(sigma, width, height, &env.paint_params, &mut rng);
+            }
+        }
+        env.calculate_fitness_for_population();
+        env.select_survivors(old_pool);
+
+        state.generations += 1;
+        if state.generations < env.optimizer_params.generations_per_triangle {
+            return;
+        }
+        state.generations = 0;
+
+        // with several objectives the candidate only has to not be dominated, since a measure
+        // like the genome complexity gets worse with every triangle
+        let best = env.pool[0].clone();
+        env.calculate_scores(std::slice::from_mut(&mut state.frozen));
+        let improves = if env.is_scalarized() {
+            env.scalarize(&best) < env.scalarize(&state.frozen)
+        } else {
+            !dominates(&state.frozen, &best)
+        };
+        if improves {
+            let (thread_pool, mode) = (Arc::clone(&env.thread_pool), env.render_mode);
+            let base = env.base_canvas.as_mut().unwrap();
+            let scale = (base.image.width() as f32 / width as f32, base.image.height() as f32 / height as f32);
+            thread_pool.install(|| draw::draw_triangles(&mut base.image, &best.triangles[base.triangles..], scale, mode));
+            base.triangles = best.triangles.len();
+            state.frozen = best;
+        }
+
+        if state.frozen.triangles.len() < env.num_triangles {
+            start_candidates(env, &state.frozen);
+        } else {
+            env.pool = vec![state.frozen.clone(); env.pool_size];
+            env.rank_pool();
+        }
+    }
+}
+
+// a pool of the frozen triangles with one random candidate each, evaluated and ranked
+fn start_candidates(env: &mut Environment, frozen: &Vector) {
+    env.pool = (0..env.pool_size)
+        .map(|_| {
+            let mut vector = frozen.clone();
+            vector.triangles.push(Triangle::generate_random_triangle(env.target_width, env.target_height, &env.paint_params));
+            vector
+        })
+        .collect();
+    env.calculate_fitness_for_population();
+    env.rank_pool();
+}
+
+fn evaluate(env: &mut Environment, vector: &Vector) -> Vector {
+    let pool = std::mem::replace(&mut env.pool, vec![vector.clone()]);
+    env.calculate_fitness_for_population();
+    let vector = env.pool.pop().unwrap();
+    env.pool = pool;
+    vector
+}
@@ -0,0 +1,50 @@
+use rand::Rng;
+
+use crate::optimizer::{mutate_pool, Optimizer, OptimizerParams};
+use crate::vector::Environment;
+
+// one annealing chain per vector of the pool on the scalarised score, the temperature is shared
+// and cools geometrically every generation
+pub struct SimulatedAnnealing {
+    temperature: f64,
+}
+
+impl SimulatedAnnealing {
+    pub fn new(params: &OptimizerParams) -> Self {
+        Self { temperature: params.initial_temperature }
+    }
+}
+
+impl Optimizer for SimulatedAnnealing {
+    fn step(&mut self, env: &mut Environment) {
+        let mut rng = rand::thread_rng();
+        let mut old_pool = env.pool.clone();
+        mutate_pool(env, env.optimizer_params.mutation_sigma);
+        env.calculate_fitness_for_population();
+        env.calculate_scores(&mut old_pool);
+
+        // the worsening is taken relative to the current energy, so the same temperature works
+        // for any measure and normalisation
+        let accepted: Vec<bool> = env
+            .pool
+            .iter()
+            .zip(old_pool.iter())
+            .map(|(vector, old_vector)| {
+                let (energy, old_energy) = (env.scalarize(vector), env.scalarize(old_vector));
+                if energy <= old_energy {
+                    return true;
+                }
+                let worsening = (energy - old_energy) / old_energy.abs().max(f64::EPSILON);
+                energy.is_finite() && rng.gen::<f64>() < (-worsening / self.temperature.max(f64::MIN_POSITIVE)).exp()
+            })
+            .collect();
+        for ((vector, old_vector), accept) in env.pool.iter_mut().zip(old_pool).zip(accepted) {
+            if !accept {
+                *vector = old_vector;
+            }
+        }
+
+        env.rank_pool();
+        self.temperature *= env.optimizer_params.cooling_rate;
+    }
+}
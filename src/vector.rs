@@ -1,9 +1,362 @@
 use rand::Rng;
-use image::{Rgba, RgbaImage};
+use rand::seq::SliceRandom;
+use image::{imageops, Rgba, RgbaImage};
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use statrs::distribution::Normal;
 use std::sync::Arc;
+use crate::draw::{BaseCanvas, BlendMode, RenderMode};
+use crate::optimizer::{Optimizer, OptimizerParams};
+use crate::fitness::delta_e::DeltaEParams;
+use crate::fitness::compression::CompressionParams;
+use crate::fitness::fractal::FractalParams;
+use crate::fitness::color::ColorParams;
+use crate::fitness::spectral::SpectralParams;
+use crate::fitness::bell_curve::BellCurveParams;
+use crate::fitness::histogram::HistogramParams;
+use crate::fitness::edge_compare::EdgeCompareParams;
 
 pub type FitnessFunction<'a> = Arc<dyn Fn(&mut Environment<'a>, usize) + Send + Sync + 'a>;
 
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Direction {
+    Minimize,
+    Maximize,
+}
+
+#[derive(Clone)]
+pub struct Objective<'a> {
+    pub name: String,
+    pub function: FitnessFunction<'a>,
+    pub direction: Direction,
+    pub weight: f64,
+}
+
+impl<'a> Objective<'a> {
+    pub fn new(name: &str, function: FitnessFunction<'a>, direction: Direction) -> Self {
+        Objective {
+            name: name.to_string(),
+            function,
+            direction,
+            weight: 1.0,
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Normalization {
+    None,
+    MinMax,
+    ZScore,
+}
+
+// how several objectives are combined, `None` keeps them apart and uses NSGA-II
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Scalarization {
+    None,
+    WeightedSum,
+    Tchebycheff,
+}
+
+// how the three DE parents are picked: a tournament with the crowded comparison,
+// uniformly at random as in classic DE, or linear ranking
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum ParentSelection {
+    Tournament,
+    Random,
+    RankBased,
+}
+
+// resolution the measures work at, relative to the target image. with coarse to fine the
+// evolution starts at `initial_scale` and climbs to `eval_scale` in geometric steps
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct ResolutionSchedule {
+    pub eval_scale: f64,
+    pub coarse_to_fine: bool,
+    pub initial_scale: f64,
+    pub levels: usize,
+    pub generations_per_level: usize,
+}
+
+impl Default for ResolutionSchedule {
+    fn default() -> Self {
+        Self {
+            eval_scale: 1.0,
+            coarse_to_fine: false,
+            initial_scale: 0.25,
+            levels: 3,
+            generations_per_level: 50,
+        }
+    }
+}
+
+impl ResolutionSchedule {
+    pub fn scale_at(&self, generation: usize) -> f64 {
+        let final_scale = self.eval_scale.clamp(0.01, 1.0);
+        if !self.coarse_to_fine || self.levels < 2 {
+            return final_scale;
+        }
+        let initial_scale = self.initial_scale.clamp(0.01, final_scale);
+        let level = (generation / self.generations_per_level.max(1)).min(self.levels - 1);
+        initial_scale * (final_scale / initial_scale).powf(level as f64 / (self.levels - 1) as f64)
+    }
+}
+
+// how triangles of genomes with different lengths are paired in the DE arithmetic: by
+// position, leaving the extra triangles of the longer genome alone, or by relative depth so
+// the whole genomes are stretched over each other
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum Alignment {
+    Positional,
+    Proportional,
+}
+
+impl Alignment {
+    // index in a genome of length `other_len` paired with triangle `i` of a genome of length `len`
+    fn index(&self, i: usize, len: usize, other_len: usize) -> Option<usize> {
+        match self {
+            Alignment::Positional => (i < other_len).then_some(i),
+            Alignment::Proportional if other_len == 0 => None,
+            Alignment::Proportional => Some((((i as f64 + 0.5) * other_len as f64 / len as f64) as usize).min(other_len - 1)),
+        }
+    }
+}
+
+// positional crossover copies mutant triangles into the slots of the target. the order preserving
+// one copies a block of consecutive mutant layers instead, in the mutant's order and at its depth
+// there, and the target triangles that are kept close up around it in their own order, so the
+// paint order of both parents is passed on
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum LayerCrossover {
+    Positional,
+    OrderPreserving,
+}
+
+// with a variable length every trial vector goes through the structural mutations after the
+// crossover, otherwise all genomes keep `num_triangles` triangles
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct GenomeParams {
+    pub variable_length: bool,
+    pub min_triangles: usize,
+    pub max_triangles: usize,
+    pub insert_probability: f64,
+    pub delete_probability: f64,
+    pub duplicate_probability: f64,
+    pub reorder_probability: f64,
+    pub alignment: Alignment,
+    pub swap_probability: f64,
+    pub shift_probability: f64,
+    pub max_shift: usize,
+    pub crossover: LayerCrossover,
+}
+
+impl Default for GenomeParams {
+    fn default() -> Self {
+        Self {
+            variable_length: false,
+            min_triangles: 10,
+            max_triangles: 500,
+            insert_probability: 0.05,
+            delete_probability: 0.05,
+            duplicate_probability: 0.02,
+            reorder_probability: 0.05,
+            alignment: Alignment::Proportional,
+            swap_probability: 0.0,
+            shift_probability: 0.0,
+            max_shift: 10,
+            crossover: LayerCrossover::Positional,
+        }
+    }
+}
+
+// alpha bounds of every triangle, and either one blend mode for the whole run or a blend mode
+// gene per triangle that mutates with `blend_mutation_probability`
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct PaintParams {
+    pub min_alpha: u8,
+    pub max_alpha: u8,
+    pub blend_mode: BlendMode,
+    pub evolve_blend_mode: bool,
+    pub blend_mutation_probability: f64,
+}
+
+impl Default for PaintParams {
+    fn default() -> Self {
+        Self {
+            min_alpha: 50,
+            max_alpha: 130,
+            blend_mode: BlendMode::SourceOver,
+            evolve_blend_mode: false,
+            blend_mutation_probability: 0.01,
+        }
+    }
+}
+
+impl PaintParams {
+    fn alpha_range(&self) -> (u8, u8) {
+        (self.min_alpha.min(self.max_alpha), self.min_alpha.max(self.max_alpha))
+    }
+
+    fn random_blend_mode(&self) -> BlendMode {
+        if self.evolve_blend_mode {
+            BlendMode::ALL[rand::thread_rng().gen_range(0..BlendMode::ALL.len())]
+        } else {
+            self.blend_mode
+        }
+    }
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum LocalSearchTarget {
+    Best,
+    FirstFront,
+}
+
+// memetic refinement: every `interval` generations the target vectors are hill climbed with up
+// to `budget` extra evaluations, nudging one vertex or color channel by `step` of its range
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct LocalSearchParams {
+    pub enabled: bool,
+    pub target: LocalSearchTarget,
+    pub interval: usize,
+    pub budget: usize,
+    pub step: f64,
+}
+
+impl Default for LocalSearchParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: LocalSearchTarget::Best,
+            interval: 10,
+            budget: 20,
+            step: 0.02,
+        }
+    }
+}
+
+// the canvas is transparent, filled with one user chosen color or with the color evolved in
+// each genome. measures and exports can each use the composited or the transparent canvas
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Debug)]
+pub enum BackgroundMode {
+    Transparent,
+    Fixed,
+    Evolved,
+}
+
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(Debug)]
+pub struct BackgroundParams {
+    pub mode: BackgroundMode,
+    pub color: [u8; 3],
+    pub composite_measures: bool,
+    pub composite_export: bool,
+}
+
+impl Default for BackgroundParams {
+    fn default() -> Self {
+        Self {
+            mode: BackgroundMode::Transparent,
+            color: [255, 255, 255],
+            composite_measures: true,
+            composite_export: true,
+        }
+    }
+}
+
+// running statistics of the raw values of one objective over every evaluation of the run
+#[derive(Clone)]
+#[derive(Debug)]
+struct ObjectiveStats {
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl ObjectiveStats {
+    fn new() -> Self {
+        ObjectiveStats {
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    // welford's online update
+    fn update(&mut self, value: f64) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    fn normalize(&self, value: f64, normalization: Normalization) -> f64 {
+        match normalization {
+            Normalization::None => value,
+            Normalization::MinMax => {
+                let range = self.max - self.min;
+                if range > 0.0 { (value - self.min) / range } else { 0.0 }
+            }
+            Normalization::ZScore => {
+                let std_dev = if self.count > 1 { (self.m2 / (self.count - 1) as f64).sqrt() } else { 0.0 };
+                if std_dev > 0.0 { (value - self.mean) / std_dev } else { 0.0 }
+            }
+        }
+    }
+}
+
+// INFO: Add the settings of new fitness functions here
+#[derive(Clone)]
+#[derive(Default)]
+pub struct FitnessParams {
+    pub delta_e: DeltaEParams,
+    pub compression: CompressionParams,
+    pub fractal: FractalParams,
+    pub color: ColorParams,
+    pub spectral: SpectralParams,
+    pub bell_curve: BellCurveParams,
+    pub histogram: HistogramParams,
+    pub edge_compare: EdgeCompareParams,
+}
+
 #[derive(Clone)]
 #[derive(Copy)]
 #[derive(Debug)]
@@ -19,11 +372,12 @@ pub struct Triangle {
     pub vertex1: Vertex,
     pub vertex2: Vertex,
     pub vertex3: Vertex,
-    pub color: Rgba<u8>
+    pub color: Rgba<u8>,
+    pub blend: BlendMode
 }
 
 impl Triangle {
-    pub fn generate_random_triangle(width: u32, height: u32) -> Self {
+    pub fn generate_random_triangle(width: u32, height: u32, params: &PaintParams) -> Self {
         let mut rng = rand::thread_rng();
 
         let vertex1 = Vertex {
@@ -43,92 +397,52 @@ impl Triangle {
             vertex3.y = rng.gen_range(0..height);
         }
 
-        let color: Rgba<u8> = Rgba::from([rng.gen(), rng.gen(), rng.gen(), rng.gen_range(50..130)]);
+        let (min_alpha, max_alpha) = params.alpha_range();
+        let color: Rgba<u8> = Rgba::from([rng.gen(), rng.gen(), rng.gen(), rng.gen_range(min_alpha..=max_alpha)]);
 
         Triangle {
             vertex1,
             vertex2,
             vertex3,
-            color
+            color,
+            blend: params.random_blend_mode()
         }
     }
 
-    fn difference_between_triangles(t1: &Triangle, t2: &Triangle) -> Triangle {
-        let vertex1 = Vertex {
-            x: t1.vertex1.x - t2.vertex1.x,
-            y: t1.vertex1.y - t2.vertex1.y
-        };
-
-        let vertex2 = Vertex {
-            x: t1.vertex2.x - t2.vertex2.x,
-            y: t1.vertex2.y - t2.vertex2.y
+    // xr1 + F * (xr2 - xr3) for every coordinate and channel, in signed arithmetic so negative
+    // differences are not wrapped around
+    fn differential_mutation(t1: &Triangle, t2: &Triangle, t3: &Triangle, scaling_factor: f64, width: u32, height: u32, params: &PaintParams) -> Triangle {
+        let combine = |a: u32, b: u32, c: u32, max: u32| differential(a, b, c, scaling_factor, max);
+        let vertex = |v1: &Vertex, v2: &Vertex, v3: &Vertex| Vertex {
+            x: combine(v1.x, v2.x, v3.x, width - 1),
+            y: combine(v1.y, v2.y, v3.y, height - 1),
         };
-
-        let vertex3 = Vertex {
-            x: t1.vertex3.x - t2.vertex3.x,
-            y: t1.vertex3.y - t2.vertex3.y
+        let channel = |c: usize| combine(t1.color[c] as u32, t2.color[c] as u32, t3.color[c] as u32, 255) as u8;
+
+        let mut triangle = Triangle {
+            vertex1: vertex(&t1.vertex1, &t2.vertex1, &t3.vertex1),
+            vertex2: vertex(&t1.vertex2, &t2.vertex2, &t3.vertex2),
+            vertex3: vertex(&t1.vertex3, &t2.vertex3, &t3.vertex3),
+            color: Rgba::from([channel(0), channel(1), channel(2), channel(3)]),
+            blend: t1.blend
         };
-
-        let color = Rgba::from([
-            t1.color[0] - t2.color[0],
-            t1.color[1] - t2.color[1],
-            t1.color[2] - t2.color[2],
-            t1.color[3] - t2.color[3]
-        ]);
-
-        Triangle {
-            vertex1,
-            vertex2,
-            vertex3,
-            color
-        }
+        Triangle::clamp_triangle(&mut triangle, width, height, params);
+        triangle
     }
 
-    fn sum_triangles(t1: &Triangle, t2: &Triangle) -> Triangle {
-        let vertex1 = Vertex {
-            x: t1.vertex1.x + t2.vertex1.x,
-            y: t1.vertex1.y + t2.vertex1.y
-        };
-
-        let vertex2 = Vertex {
-            x: t1.vertex2.x + t2.vertex2.x,
-            y: t1.vertex2.y + t2.vertex2.y
-        };
-
-        let vertex3 = Vertex {
-            x: t1.vertex3.x + t2.vertex3.x,
-            y: t1.vertex3.y + t2.vertex3.y
-        };
-
-        let color = Rgba::from([
-            t1.color[0] + t2.color[0],
-            t1.color[1] + t2.color[1],
-            t1.color[2] + t2.color[2],
-            t1.color[3] + t2.color[3]
-        ]);
-
-        Triangle {
-            vertex1,
-            vertex2,
-            vertex3,
-            color
+    pub fn gaussian_mutation(&mut self, sigma: f64, width: u32, height: u32, params: &PaintParams, rng: &mut impl Rng) {
+        for vertex in [&mut self.vertex1, &mut self.vertex2, &mut self.vertex3] {
+            vertex.x = perturb(vertex.x, sigma, width - 1, rng);
+            vertex.y = perturb(vertex.y, sigma, height - 1, rng);
         }
+        for channel in self.color.0.iter_mut() {
+            *channel = perturb(*channel as u32, sigma, 255, rng) as u8;
+        }
+        Triangle::clamp_triangle(self, width, height, params);
     }
 
-    fn apply_scaling_factor_in_triangle(t: &mut Triangle, scaling_factor: f64) -> () {
-        t.vertex1.x = (t.vertex1.x as f64 * scaling_factor) as u32;
-        t.vertex1.y = (t.vertex1.y as f64 * scaling_factor) as u32;
-        t.vertex2.x = (t.vertex2.x as f64 * scaling_factor) as u32;
-        t.vertex2.y = (t.vertex2.y as f64 * scaling_factor) as u32;
-        t.vertex3.x = (t.vertex3.x as f64 * scaling_factor) as u32;
-        t.vertex3.y = (t.vertex3.y as f64 * scaling_factor) as u32;
-        t.color[0] = (t.color[0] as f64 * scaling_factor) as u8;
-        t.color[1] = (t.color[1] as f64 * scaling_factor) as u8;
-        t.color[2] = (t.color[2] as f64 * scaling_factor) as u8;
-        t.color[3] = (t.color[3] as f64 * scaling_factor) as u8;
-    }
-
-    fn clamp_triangle(t: &mut Triangle, width: u32, height: u32) -> () {
+    fn clamp_triangle(t: &mut Triangle, width: u32, height: u32, params: &PaintParams) {
+        let (min_alpha, max_alpha) = params.alpha_range();
         t.vertex1.x = t.vertex1.x.clamp(0, width-1);
         t.vertex1.y = t.vertex1.y.clamp(0, height-1);
         t.vertex2.x = t.vertex2.x.clamp(0, width-1);
@@ -138,97 +452,266 @@ impl Triangle {
         t.color[0] = t.color[0].clamp(0, 255);
         t.color[1] = t.color[1].clamp(0, 255);
         t.color[2] = t.color[2].clamp(0, 255);
-        t.color[3] = t.color[3].clamp(50, 130);
+        t.color[3] = t.color[3].clamp(min_alpha, max_alpha);
     }
 }
 
+// a + scaling_factor * (b - c) rounded and clamped to [0, max]
+fn differential(a: u32, b: u32, c: u32, scaling_factor: f64, max: u32) -> u32 {
+    (a as f64 + scaling_factor * (b as f64 - c as f64)).round().clamp(0.0, max as f64) as u32
+}
+
+// value plus gaussian noise with standard deviation `sigma * max`, rounded and clamped to [0, max]
+fn perturb(value: u32, sigma: f64, max: u32, rng: &mut impl Rng) -> u32 {
+    let noise: f64 = rng.sample(Normal::new(0.0, 1.0).unwrap());
+    (value as f64 + noise * sigma * max as f64).round().clamp(0.0, max as f64) as u32
+}
+
 #[derive(Clone)]
 #[derive(Debug)]
 pub struct Vector {
     pub triangles: Vec<Triangle>,
+    pub background: [u8; 3],
     pub fitness: Vec<f64>,
+    pub scores: Vec<f64>,
     pub rank: usize,
     pub crowding_distance: f64
 }
 
 impl Vector {
-    fn generate_random_vector(width: u32, height: u32, num_triangles: usize, num_objectives: usize) -> Self {
+    fn generate_random_vector(width: u32, height: u32, num_triangles: usize, params: &PaintParams, num_objectives: usize) -> Self {
         let mut triangles = Vec::with_capacity(num_triangles);
         for _ in 0..num_triangles {
-            triangles.push(Triangle::generate_random_triangle(width, height));
+            triangles.push(Triangle::generate_random_triangle(width, height, params));
         }
 
         Vector {
             triangles,
+            background: rand::thread_rng().gen(),
             fitness: vec![0.0; num_objectives],
+            scores: vec![0.0; num_objectives],
             rank: 0,
             crowding_distance: 0.0
         }
     }
 
-    fn generate_mutant_vector(xr1: &Vector, xr2: &Vector, xr3: &Vector, scaling_factor: f64, width: u32, height: u32, num_objectives: usize) -> Vector {
-        // xr2 - xr3
-        let mut difference_vector: Vector = Vector::difference_between_vectors(xr2, xr3, num_objectives);
-        // F * (xr2 - xr3)
-        for triangle in difference_vector.triangles.iter_mut() {
-            Triangle::apply_scaling_factor_in_triangle(triangle, scaling_factor);
-        }
-        // xr1 + F * (xr2 - xr3)
-        let mut mutant_vector: Vector = Vector::sum_vectors(xr1, &difference_vector, num_objectives);
+    pub fn generate_mutant_vector(xr1: &Vector, xr2: &Vector, xr3: &Vector, env: &Environment) -> Vector {
+        let (scaling_factor, width, height) = (env.scaling_factor, env.target_width, env.target_height);
+        let alignment = env.genome_params.alignment;
+        // xr1 + scaling_factor * (xr2 - xr3), triangles of xr1 without a counterpart in both
+        // xr2 and xr3 are copied unchanged
+        let triangles = xr1
+            .triangles
+            .iter()
+            .enumerate()
+            .map(|(i, t1)| {
+                let len = xr1.triangles.len();
+                match (alignment.index(i, len, xr2.triangles.len()), alignment.index(i, len, xr3.triangles.len())) {
+                    (Some(i2), Some(i3)) => Triangle::differential_mutation(
+                        t1,
+                        &xr2.triangles[i2],
+                        &xr3.triangles[i3],
+                        scaling_factor,
+                        width,
+                        height,
+                        &env.paint_params
+                    ),
+                    _ => *t1,
+                }
+            })
+            .collect();
+        let background = std::array::from_fn(|c| {
+            differential(xr1.background[c] as u32, xr2.background[c] as u32, xr3.background[c] as u32, scaling_factor, 255) as u8
+        });
 
-        // clamp final triangles
-        for triangle in mutant_vector.triangles.iter_mut() {
-            Triangle::clamp_triangle(triangle, width, height);
+        Vector {
+            triangles,
+            background,
+            fitness: vec![0.0; env.num_objectives],
+            scores: vec![0.0; env.num_objectives],
+            rank: 0,
+            crowding_distance: 0.0
         }
-
-        mutant_vector
     }
 
-    fn difference_between_vectors(vector1: &Vector, vector2: &Vector, num_objectives: usize) -> Vector {
-        let mut triangles = Vec::with_capacity(vector1.triangles.len());
-        for i in 0..vector1.triangles.len() {
-            triangles.push(Triangle::difference_between_triangles(&vector1.triangles[i], &vector2.triangles[i]));
-        }
+    // the trial keeps the length of the target, `vector1` is the mutant
+    pub fn crossover(vector1: &Vector, vector2: &Vector, crossover_probability: f64, params: &GenomeParams, num_objectives: usize) -> Self {
+        let mut rng = rand::thread_rng();
+        let (len1, len2) = (vector1.triangles.len(), vector2.triangles.len());
+        // mutant triangle paired with each target triangle that is replaced
+        let picked: Vec<Option<usize>> = (0..len2)
+            .map(|i| params.alignment.index(i, len2, len1).filter(|_| rng.gen_bool(crossover_probability)))
+            .collect();
+
+        let new_triangles = match params.crossover {
+            LayerCrossover::Positional => picked
+                .iter()
+                .zip(vector2.triangles.iter())
+                .map(|(j, triangle)| j.map_or(*triangle, |j| vector1.triangles[j]))
+                .collect(),
+            LayerCrossover::OrderPreserving => {
+                // as many target triangles as the block holds are replaced
+                let count = picked.iter().filter(|j| j.is_some()).count().min(len1);
+                let mut replaced = 0;
+                let mut triangles: Vec<Triangle> = Vec::with_capacity(len2);
+                for (j, triangle) in picked.iter().zip(vector2.triangles.iter()) {
+                    if j.is_some() && replaced < count {
+                        replaced += 1;
+                    } else {
+                        triangles.push(*triangle);
+                    }
+                }
+                let start = rng.gen_range(0..=len1 - count);
+                let depth = if len1 > count { start * (len2 - count) / (len1 - count) } else { 0 };
+                triangles.splice(depth..depth, vector1.triangles[start..start + count].iter().copied());
+                triangles
+            }
+        };
+        let background = if rng.gen_bool(crossover_probability) { vector1.background } else { vector2.background };
 
         Vector {
-            triangles,
+            triangles: new_triangles,
+            background,
             fitness: vec![0.0; num_objectives],
+            scores: vec![0.0; num_objectives],
             rank: 0,
             crowding_distance: 0.0
         }
     }
 
-    fn sum_vectors(vector1: &Vector, vector2: &Vector, num_objectives: usize) -> Vector {
-        let mut triangles = Vec::with_capacity(vector1.triangles.len());
-        for i in 0..vector1.triangles.len() {
-            triangles.push(Triangle::sum_triangles(&vector1.triangles[i], &vector2.triangles[i]));
+    // moves one vertex of a random triangle from `first` on or changes one of its channels, by
+    // gaussian noise of `step` times the range
+    fn nudge(&mut self, first: usize, step: f64, width: u32, height: u32, params: &PaintParams) {
+        let mut rng = rand::thread_rng();
+        if self.triangles.len() <= first {
+            return;
         }
 
-        Vector {
-            triangles,
-            fitness: vec![0.0; num_objectives],
-            rank: 0,
-            crowding_distance: 0.0
+        let index = rng.gen_range(first..self.triangles.len());
+        let triangle = &mut self.triangles[index];
+        match rng.gen_range(0..7) {
+            vertex @ 0..=2 => {
+                let vertex = match vertex {
+                    0 => &mut triangle.vertex1,
+                    1 => &mut triangle.vertex2,
+                    _ => &mut triangle.vertex3,
+                };
+                vertex.x = perturb(vertex.x, step, width - 1, &mut rng);
+                vertex.y = perturb(vertex.y, step, height - 1, &mut rng);
+            }
+            channel => triangle.color[channel - 3] = perturb(triangle.color[channel - 3] as u32, step, 255, &mut rng) as u8,
         }
+        Triangle::clamp_triangle(triangle, width, height, params);
     }
 
-    fn crossover(vector1: &Vector, vector2: &Vector, crossover_probability: f64, num_objectives: usize) -> Self {
+    // perturbs every coordinate and channel of each triangle with probability `rate`, by gaussian
+    // noise of `sigma` times the range. at least one triangle always changes
+    pub fn gaussian_mutation(&mut self, rate: f64, sigma: f64, width: u32, height: u32, params: &PaintParams) {
         let mut rng = rand::thread_rng();
-        let mut new_triangles = Vec::with_capacity(vector1.triangles.len());
+        if self.triangles.is_empty() {
+            return;
+        }
 
-        for i in 0..vector1.triangles.len() {
-            if rng.gen_bool(crossover_probability) {
-                new_triangles.push(vector1.triangles[i]);
-            } else {
-                new_triangles.push(vector2.triangles[i]);
+        let forced = rng.gen_range(0..self.triangles.len());
+        for (i, triangle) in self.triangles.iter_mut().enumerate() {
+            if i == forced || rng.gen_bool(rate) {
+                triangle.gaussian_mutation(sigma, width, height, params, &mut rng);
             }
         }
+        if rng.gen_bool(rate) {
+            for channel in self.background.iter_mut() {
+                *channel = perturb(*channel as u32, sigma, 255, &mut rng) as u8;
+            }
+        }
+    }
 
-        Vector {
-            triangles: new_triangles,
-            fitness: vec![0.0; num_objectives],
-            rank: 0,
-            crowding_distance: 0.0
+    // every coordinate and channel scaled to [0, 1], triangle after triangle and the background
+    // last, for the optimisers that search a real valued space
+    pub fn to_genes(&self, width: u32, height: u32) -> Vec<f64> {
+        let (max_x, max_y) = ((width - 1).max(1) as f64, (height - 1).max(1) as f64);
+        let mut genes = Vec::with_capacity(self.triangles.len() * 10 + 3);
+        for triangle in &self.triangles {
+            for vertex in [&triangle.vertex1, &triangle.vertex2, &triangle.vertex3] {
+                genes.push(vertex.x as f64 / max_x);
+                genes.push(vertex.y as f64 / max_y);
+            }
+            genes.extend(triangle.color.0.iter().map(|&channel| channel as f64 / 255.0));
+        }
+        genes.extend(self.background.iter().map(|&channel| channel as f64 / 255.0));
+        genes
+    }
+
+    // inverse of `to_genes`, rounded and clamped. blend modes and the length come from `template`
+    pub fn from_genes(genes: &[f64], template: &Vector, width: u32, height: u32, params: &PaintParams) -> Vector {
+        let (max_x, max_y) = ((width - 1).max(1), (height - 1).max(1));
+        let scale = |gene: f64, max: u32| (gene * max as f64).round().clamp(0.0, max as f64) as u32;
+        let mut vector = template.clone();
+        for (triangle, genes) in vector.triangles.iter_mut().zip(genes.chunks_exact(10)) {
+            triangle.vertex1 = Vertex { x: scale(genes[0], max_x), y: scale(genes[1], max_y) };
+            triangle.vertex2 = Vertex { x: scale(genes[2], max_x), y: scale(genes[3], max_y) };
+            triangle.vertex3 = Vertex { x: scale(genes[4], max_x), y: scale(genes[5], max_y) };
+            triangle.color = Rgba::from(std::array::from_fn(|c| scale(genes[6 + c], 255) as u8));
+            Triangle::clamp_triangle(triangle, width, height, params);
+        }
+        let background = &genes[genes.len() - 3..];
+        vector.background = std::array::from_fn(|c| scale(background[c], 255) as u8);
+        vector
+    }
+
+    // moves triangles up and down the paint order, neighbours swap with `swap_probability` each
+    // and one triangle may be shifted by up to `max_shift` layers
+    fn layer_mutation(&mut self, params: &GenomeParams) {
+        let mut rng = rand::thread_rng();
+        let len = self.triangles.len();
+        if len < 2 {
+            return;
+        }
+
+        for i in 0..len - 1 {
+            if rng.gen_bool(params.swap_probability) {
+                self.triangles.swap(i, i + 1);
+            }
+        }
+        if params.max_shift > 0 && rng.gen_bool(params.shift_probability) {
+            let from = rng.gen_range(0..len);
+            let shift = rng.gen_range(-(params.max_shift as isize)..=params.max_shift as isize);
+            let to = (from as isize + shift).clamp(0, len as isize - 1) as usize;
+            let triangle = self.triangles.remove(from);
+            self.triangles.insert(to, triangle);
+        }
+    }
+
+    // each triangle switches to a random blend mode with `blend_mutation_probability`
+    fn blend_mutation(&mut self, params: &PaintParams) {
+        let mut rng = rand::thread_rng();
+        for triangle in self.triangles.iter_mut() {
+            if rng.gen_bool(params.blend_mutation_probability) {
+                triangle.blend = params.random_blend_mode();
+            }
+        }
+    }
+
+    // insert, delete, duplicate and move single triangles, each with its own probability and
+    // without leaving the length bounds
+    fn structural_mutation(&mut self, params: &GenomeParams, paint_params: &PaintParams, width: u32, height: u32) {
+        let mut rng = rand::thread_rng();
+        let min_triangles = params.min_triangles.max(1);
+
+        if self.triangles.len() < params.max_triangles && rng.gen_bool(params.insert_probability) {
+            let position = rng.gen_range(0..=self.triangles.len());
+            self.triangles.insert(position, Triangle::generate_random_triangle(width, height, paint_params));
+        }
+        if self.triangles.len() > min_triangles && rng.gen_bool(params.delete_probability) {
+            let position = rng.gen_range(0..self.triangles.len());
+            self.triangles.remove(position);
+        }
+        if !self.triangles.is_empty() && self.triangles.len() < params.max_triangles && rng.gen_bool(params.duplicate_probability) {
+            let position = rng.gen_range(0..self.triangles.len());
+            self.triangles.insert(position + 1, self.triangles[position]);
+        }
+        if self.triangles.len() > 1 && rng.gen_bool(params.reorder_probability) {
+            let triangle = self.triangles.remove(rng.gen_range(0..self.triangles.len()));
+            self.triangles.insert(rng.gen_range(0..=self.triangles.len()), triangle);
         }
     }
 }
@@ -241,14 +724,35 @@ pub struct Environment<'a> {
     pub target_img: RgbaImage,
     pub target_width: u32,
     pub target_height: u32,
+    pub eval_img: RgbaImage,
+    pub eval_width: u32,
+    pub eval_height: u32,
+    pub resolution_schedule: ResolutionSchedule,
+    pub genome_params: GenomeParams,
+    pub background_params: BackgroundParams,
+    pub paint_params: PaintParams,
+    pub local_search_params: LocalSearchParams,
+    pub optimizer_params: OptimizerParams,
+    optimizer: Option<Box<dyn Optimizer>>,
+    pub generation: usize,
     pub num_triangles: usize,
     pub num_threads: usize,
+    pub thread_pool: Arc<ThreadPool>,
+    pub render_mode: RenderMode,
+    pub base_canvas: Option<BaseCanvas>,
     pub num_objectives: usize,
-    pub fitness_functions: Vec<FitnessFunction<'a>>,
+    pub objectives: Vec<Objective<'a>>,
+    pub fitness_params: FitnessParams,
+    pub normalization: Normalization,
+    pub scalarization: Scalarization,
+    objective_stats: Vec<ObjectiveStats>,
+    pub parent_selection: ParentSelection,
+    pub non_finite_counts: Vec<usize>,
     pub tournament_size: usize
 }
 
 impl<'a> Environment<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         pool_size: usize,
         scaling_factor: f64,
@@ -256,9 +760,9 @@ impl<'a> Environment<'a> {
         target_img: RgbaImage,
         num_triangles: usize,
         num_threads: usize,
-        fitness_functions: Vec<FitnessFunction<'a>>,
+        objectives: Vec<Objective<'a>>,
         tournament_size: usize
-    ) -> Self {
+    ) -> Result<Self, ThreadPoolBuildError> {
         if pool_size < 4 {
             panic!("Pool size must be at least 4");
         }
@@ -266,139 +770,460 @@ impl<'a> Environment<'a> {
         let target_height = target_img.height();
 
         let pool = Vec::with_capacity(pool_size);
-        let num_objectives = fitness_functions.len();
+        let num_objectives = objectives.len();
+        // every environment renders and evaluates on its own threads instead of the global pool,
+        // so several experiments can share a machine. replace it to share another one's pool
+        let thread_pool = Arc::new(ThreadPoolBuilder::new().num_threads(num_threads).build()?);
 
-        Environment {
+        Ok(Environment {
             pool,
             pool_size,
             scaling_factor,
             crossover_probability,
+            eval_img: target_img.clone(),
+            eval_width: target_width,
+            eval_height: target_height,
+            resolution_schedule: ResolutionSchedule::default(),
+            genome_params: GenomeParams::default(),
+            background_params: BackgroundParams::default(),
+            paint_params: PaintParams::default(),
+            local_search_params: LocalSearchParams::default(),
+            optimizer_params: OptimizerParams::default(),
+            optimizer: None,
+            generation: 0,
             target_img,
             target_width,
             target_height,
             num_triangles,
             num_threads,
+            thread_pool,
+            render_mode: RenderMode::Scanline,
+            base_canvas: None,
             num_objectives,
-            fitness_functions,
+            objectives,
+            fitness_params: FitnessParams::default(),
+            normalization: Normalization::None,
+            scalarization: Scalarization::None,
+            objective_stats: vec![ObjectiveStats::new(); num_objectives],
+            parent_selection: ParentSelection::Tournament,
+            non_finite_counts: vec![0; num_objectives],
             tournament_size
-        }
+        })
+    }
+
+    // color the canvas of `vector` starts from, transparent unless `composite` is set
+    pub fn background(&self, vector: &Vector, composite: bool) -> Rgba<u8> {
+        let [r, g, b] = match self.background_params.mode {
+            BackgroundMode::Fixed if composite => self.background_params.color,
+            BackgroundMode::Evolved if composite => vector.background,
+            _ => return Rgba([0, 0, 0, 0]),
+        };
+        Rgba([r, g, b, 255])
     }
 
-    pub fn generate_initial_pool(&mut self) -> () {
+    pub fn generate_initial_pool(&mut self) {
         for _ in 0..self.pool_size {
             self.pool.push(
                 Vector::generate_random_vector(
                     self.target_width,
                     self.target_height,
                     self.num_triangles,
+                    &self.paint_params,
                     self.num_objectives
                 )
             );
         }
+        self.update_resolution();
         self.calculate_fitness_for_population();
+        self.rank_pool();
+        self.optimizer = Some(self.optimizer_params.create());
+    }
+
+    // moves the evaluation to the resolution of the current generation, returns whether it
+    // changed. values from different resolutions are not comparable, so the statistics start over
+    fn update_resolution(&mut self) -> bool {
+        let scale = self.resolution_schedule.scale_at(self.generation);
+        let width = ((self.target_width as f64 * scale).round() as u32).max(1);
+        let height = ((self.target_height as f64 * scale).round() as u32).max(1);
+        if width == self.eval_width && height == self.eval_height {
+            return false;
+        }
+
+        self.eval_img = if width == self.target_width && height == self.target_height {
+            self.target_img.clone()
+        } else {
+            imageops::resize(&self.target_img, width, height, imageops::FilterType::Triangle)
+        };
+        self.eval_width = width;
+        self.eval_height = height;
+        self.objective_stats = vec![ObjectiveStats::new(); self.num_objectives];
+        true
+    }
+
+    // assigns rank and crowding distance to the pool, ordering it from best to worst
+    pub fn rank_pool(&mut self) {
+        if self.is_scalarized() {
+            self.single_objective_selection();
+        } else {
+            self.nsga_selection(&[]);
+        }
     }
 
     pub fn calculate_fitness_for_population(&mut self) {
-        let fitness_functions = self.fitness_functions.clone(); // Clone the fitness functions
-        for (idx, fitness_fn) in fitness_functions.iter().enumerate() {
-            fitness_fn(self, idx); // Mutable borrow of `self` is now safe
+        let objectives = self.objectives.clone(); // Clone the objectives
+        let thread_pool = Arc::clone(&self.thread_pool);
+        for (idx, objective) in objectives.iter().enumerate() {
+            // values the measure forgets to set stay NaN and are reported as well
+            for vector in self.pool.iter_mut() {
+                vector.fitness[idx] = f64::NAN;
+            }
+            // rendering and evaluation inside the measure run on the environment's pool
+            thread_pool.install(|| (objective.function)(self, idx)); // Mutable borrow of `self` is now safe
+            self.validate_fitness(idx);
+        }
+
+        for vector in self.pool.iter() {
+            for (stats, fitness) in self.objective_stats.iter_mut().zip(vector.fitness.iter()) {
+                if fitness.is_finite() {
+                    stats.update(*fitness);
+                }
+            }
         }
+        let mut pool = std::mem::take(&mut self.pool);
+        self.calculate_scores(&mut pool);
+        self.pool = pool;
+    }
+
+    // NaN or infinite values are kept in the raw fitness but scored as the worst possible,
+    // they are counted for `warnings`
+    fn validate_fitness(&mut self, idx: usize) {
+        self.non_finite_counts[idx] += self.pool.iter().filter(|vector| !vector.fitness[idx].is_finite()).count();
     }
 
-    fn tournament_selection(&self, tournament_size: usize) -> &Vector {
+    pub fn warnings(&self) -> Vec<String> {
+        self.objectives
+            .iter()
+            .zip(self.non_finite_counts.iter())
+            .filter(|(_, &count)| count > 0)
+            .map(|(objective, count)| format!("{} returned {} non-finite values, treated as worst", objective.name, count))
+            .collect()
+    }
+
+    // scores are the normalised raw fitness oriented so that lower is always better,
+    // every comparison made by the engine uses them instead of the raw fitness
+    pub fn calculate_scores(&self, vectors: &mut [Vector]) {
+        for vector in vectors.iter_mut() {
+            for (idx, objective) in self.objectives.iter().enumerate() {
+                if !vector.fitness[idx].is_finite() {
+                    vector.scores[idx] = f64::INFINITY;
+                    continue;
+                }
+                let normalized = self.objective_stats[idx].normalize(vector.fitness[idx], self.normalization);
+                vector.scores[idx] = match objective.direction {
+                    Direction::Minimize => normalized,
+                    Direction::Maximize => -normalized,
+                };
+            }
+        }
+    }
+
+    pub fn is_scalarized(&self) -> bool {
+        self.num_objectives == 1 || self.scalarization != Scalarization::None
+    }
+
+    // single value of a vector used by the scalarised mode, lower is better
+    pub fn scalarize(&self, vector: &Vector) -> f64 {
+        if vector.scores.iter().any(|score| !score.is_finite()) {
+            return f64::INFINITY;
+        }
+        match self.scalarization {
+            Scalarization::None => vector.scores.iter().sum(),
+            Scalarization::WeightedSum => self
+                .objectives
+                .iter()
+                .zip(vector.scores.iter())
+                .map(|(objective, score)| objective.weight * score)
+                .sum(),
+            Scalarization::Tchebycheff => self
+                .objectives
+                .iter()
+                .zip(vector.scores.iter())
+                .zip(self.ideal_point().iter())
+                .map(|((objective, score), ideal)| objective.weight * (score - ideal).abs())
+                .fold(0.0, f64::max),
+        }
+    }
+
+    // best score seen so far for every objective
+    fn ideal_point(&self) -> Vec<f64> {
+        self.objectives
+            .iter()
+            .zip(self.objective_stats.iter())
+            .map(|(objective, stats)| match objective.direction {
+                _ if stats.count == 0 => 0.0,
+                Direction::Minimize => stats.normalize(stats.min, self.normalization),
+                Direction::Maximize => -stats.normalize(stats.max, self.normalization),
+            })
+            .collect()
+    }
+
+    // crowded comparison operator: lower rank first, then the less crowded vector
+    fn is_better(&self, a: &Vector, b: &Vector) -> bool {
+        if self.is_scalarized() {
+            self.scalarize(a) < self.scalarize(b)
+        } else {
+            a.rank < b.rank || (a.rank == b.rank && a.crowding_distance > b.crowding_distance)
+        }
+    }
+
+    // picks the indexes of the three parents of the mutant that replaces `target`
+    pub fn select_parents(&self, target: usize) -> [usize; 3] {
+        match self.parent_selection {
+            ParentSelection::Tournament => [
+                self.tournament_selection(self.tournament_size),
+                self.tournament_selection(self.tournament_size),
+                self.tournament_selection(self.tournament_size),
+            ],
+            ParentSelection::Random => self.random_selection(target),
+            ParentSelection::RankBased => [
+                self.rank_based_selection(),
+                self.rank_based_selection(),
+                self.rank_based_selection(),
+            ],
+        }
+    }
+
+    fn tournament_selection(&self, tournament_size: usize) -> usize {
         let mut rng = rand::thread_rng();
 
-        let mut best: Option<&Vector> = None;
-        for _ in 0..tournament_size {
-            let index = rng.gen_range(0..self.pool_size);
-            let candidate = &self.pool[index];
+        let mut best: Option<usize> = None;
+        for _ in 0..tournament_size.max(1) {
+            let index = rng.gen_range(0..self.pool.len());
 
             if let Some(current_best) = best {
-                if candidate.fitness < current_best.fitness {
-                    best = Some(candidate);
+                if self.is_better(&self.pool[index], &self.pool[current_best]) {
+                    best = Some(index);
                 }
             } else {
-                best = Some(candidate);
+                best = Some(index);
             }
         }
         best.unwrap()
     }
 
+    // three distinct indexes, all different from the target vector
+    fn random_selection(&self, target: usize) -> [usize; 3] {
+        let mut rng = rand::thread_rng();
+        let mut parents = [target; 3];
+        for i in 0..3 {
+            let mut index = target;
+            while index == target || parents[..i].contains(&index) {
+                index = rng.gen_range(0..self.pool.len());
+            }
+            parents[i] = index;
+        }
+        parents
+    }
+
+    // linear ranking over the pool, which is kept ordered from best to worst, so the
+    // vector at position i is picked with probability proportional to (n - i)
+    fn rank_based_selection(&self) -> usize {
+        let mut rng = rand::thread_rng();
+        let n = self.pool.len();
+        let mut ticket = rng.gen_range(0..n * (n + 1) / 2);
+        for i in 0..n {
+            let weight = n - i;
+            if ticket < weight {
+                return i;
+            }
+            ticket -= weight;
+        }
+        n - 1
+    }
+
     pub fn get_first_front(&self) -> Vec<Vector> {
+        if self.is_scalarized() && self.num_objectives > 1 {
+            let best = self.pool.iter().min_by(|a, b| self.scalarize(a).total_cmp(&self.scalarize(b)));
+            return best.cloned().into_iter().collect();
+        }
         let fronts = self.non_dominated_sort(&self.pool);
-        fronts.get(0).cloned().unwrap_or_default()
+        fronts.first().cloned().unwrap_or_default()
     }
 
     pub fn iterate(&mut self) {
-        let old_pool = self.pool.clone();
-
-        // mutate the pool via formula
-        // xr1 + scaling_factor * (xr2 - xr3)
-        for i in 0..self.pool_size {
-            let xr1 = self.tournament_selection(self.tournament_size);
-            let xr2 = self.tournament_selection(self.tournament_size);
-            let xr3 = self.tournament_selection(self.tournament_size);
-
-            let mutant_vector = Vector::generate_mutant_vector(
-                xr1,
-                xr2,
-                xr3,
-                self.scaling_factor,
-                self.target_width,
-                self.target_height,
-                self.num_objectives
-            );
+        self.generation += 1;
+        if self.update_resolution() {
+            // the survivors are evaluated again so they compete with the trials on equal terms
+            self.calculate_fitness_for_population();
+            self.rank_pool();
+        }
+        // the optimiser is taken out for the step so it can borrow the environment
+        let mut optimizer = self.optimizer.take().unwrap_or_else(|| self.optimizer_params.create());
+        optimizer.step(self);
+
+        let local_search = self.local_search_params;
+        if local_search.enabled && self.generation.is_multiple_of(local_search.interval.max(1)) {
+            let improved = self.local_search();
+            if !improved.is_empty() {
+                optimizer.accept_improved(self, &improved);
+            }
+        }
+        self.optimizer = Some(optimizer);
+    }
 
-            let trial_vector = Vector::crossover(&mutant_vector, &self.pool[i], self.crossover_probability, self.num_objectives);
-            self.pool[i] = trial_vector;
+    // hill climbs the best vector or the members of the first front, the same ones
+    // `get_first_front` reports. every round nudges each of them once, all the nudged copies are
+    // evaluated together and the ones that improve on their original replace it. returns each
+    // original that was improved with the vector that replaced it
+    fn local_search(&mut self) -> Vec<(Vector, Vector)> {
+        let params = self.local_search_params;
+        let mut selected: Vec<usize> = match params.target {
+            LocalSearchTarget::Best => vec![0],
+            LocalSearchTarget::FirstFront => (0..self.pool.len()).filter(|&i| self.pool[i].rank == 0).collect(),
+        };
+        selected.shuffle(&mut rand::thread_rng());
+        selected.truncate(params.budget);
+        if selected.is_empty() || self.pool.is_empty() {
+            return Vec::new();
         }
 
-        // calculate the fitness of the current pool
-        self.calculate_fitness_for_population();
+        // triangles drawn on the base canvas are frozen
+        let first = self.base_canvas.as_ref().map_or(0, |base| base.triangles);
+        let mut climbers: Vec<Vector> = selected.iter().map(|&i| self.pool[i].clone()).collect();
+        let mut improved = vec![false; climbers.len()];
+        let pool = std::mem::take(&mut self.pool);
+        for _ in 0..params.budget / selected.len() {
+            self.pool = climbers
+                .iter()
+                .map(|vector| {
+                    let mut neighbour = vector.clone();
+                    neighbour.nudge(first, params.step, self.target_width, self.target_height, &self.paint_params);
+                    neighbour
+                })
+                .collect();
+            self.calculate_fitness_for_population();
+            self.calculate_scores(&mut climbers);
+
+            let neighbours = std::mem::take(&mut self.pool);
+            for ((climber, neighbour), improved) in climbers.iter_mut().zip(neighbours).zip(improved.iter_mut()) {
+                let better = if self.is_scalarized() {
+                    self.scalarize(&neighbour) < self.scalarize(climber)
+                } else {
+                    dominates(&neighbour, climber)
+                };
+                if better {
+                    *climber = neighbour;
+                    *improved = true;
+                }
+            }
+        }
 
-        // select the best vectors from the old and new pool
-        for i in 0..self.pool_size {
-            if self.pool[i].fitness > old_pool[i].fitness {
-                self.pool[i] = old_pool[i].clone();
+        self.pool = pool;
+        let mut refined = Vec::new();
+        for ((&i, climber), improved) in selected.iter().zip(climbers).zip(improved) {
+            if improved {
+                refined.push((std::mem::replace(&mut self.pool[i], climber.clone()), climber));
             }
         }
+        let mut pool = std::mem::take(&mut self.pool);
+        self.calculate_scores(&mut pool);
+        self.pool = pool;
+        self.rank_pool();
+        refined
+    }
 
+    // layer, blend mode and structural mutations, applied to the offspring of every optimiser
+    // that keeps the genome free to change
+    pub fn mutate_genome(&self, vector: &mut Vector) {
+        vector.layer_mutation(&self.genome_params);
+        if self.paint_params.evolve_blend_mode {
+            vector.blend_mutation(&self.paint_params);
+        }
+        if self.genome_params.variable_length {
+            vector.structural_mutation(&self.genome_params, &self.paint_params, self.target_width, self.target_height);
+        }
+    }
+
+    // one to one replacement of the already evaluated pool: each vector replaces the one at the
+    // same position of `old_pool` unless that one is better. returns which vectors improved
+    pub fn select_survivors(&mut self, mut old_pool: Vec<Vector>) -> Vec<bool> {
+        // the normalisation may have changed, so the old pool is scored again
+        self.calculate_scores(&mut old_pool);
+
+        // a trial vector without rank replaces its parent unless the parent dominates it
+        let (keep_old, improved): (Vec<bool>, Vec<bool>) = self
+            .pool
+            .iter()
+            .zip(old_pool.iter())
+            .map(|(vector, old_vector)| {
+                if self.is_scalarized() {
+                    (self.scalarize(old_vector) < self.scalarize(vector), self.scalarize(vector) < self.scalarize(old_vector))
+                } else {
+                    (dominates(old_vector, vector), dominates(vector, old_vector))
+                }
+            })
+            .unzip();
+        for ((vector, old_vector), keep) in self.pool.iter_mut().zip(old_pool.iter()).zip(keep_old) {
+            if keep {
+                *vector = old_vector.clone();
+            }
+        }
+
+        if self.is_scalarized() {
+            self.single_objective_selection();
+        } else {
+            self.nsga_selection(&old_pool);
+        }
+        improved
+    }
 
-        if self.num_objectives == 1 {
+    // (mu + lambda) selection: the best `pool_size` vectors of the evaluated pool and `old_pool`
+    pub fn select_best_of_both(&mut self, mut old_pool: Vec<Vector>) {
+        self.calculate_scores(&mut old_pool);
+        if self.is_scalarized() {
+            self.pool.extend(old_pool);
             self.single_objective_selection();
         } else {
             self.nsga_selection(&old_pool);
         }
     }
 
-    fn calculate_crowding_distance(&mut self, front: &mut Vec<Vector>) {
+    pub fn calculate_crowding_distance(&mut self, front: &mut [Vector]) {
         let num_objectives = self.num_objectives;
         for vector in front.iter_mut() {
             vector.crowding_distance = 0.0;
         }
 
         for obj_idx in 0..num_objectives {
-            front.sort_by(|a, b| a.fitness[obj_idx].partial_cmp(&b.fitness[obj_idx]).unwrap());
+            front.sort_by(|a, b| a.scores[obj_idx].total_cmp(&b.scores[obj_idx]));
 
             let last_idx = front.len() - 1;
             front[0].crowding_distance = f64::INFINITY;
             front[last_idx].crowding_distance = f64::INFINITY;
 
-            let min_value = front[0].fitness[obj_idx];
-            let max_value = front[front.len() - 1].fitness[obj_idx];
+            // the range only covers finite scores, vectors next to a non-finite one gain nothing
+            let finite = || front.iter().map(|v| v.scores[obj_idx]).filter(|s| s.is_finite());
+            let min_value = finite().fold(f64::INFINITY, f64::min);
+            let max_value = finite().fold(f64::NEG_INFINITY, f64::max);
             let range = max_value - min_value;
 
-            if range == 0.0 {continue;}
+            if !range.is_finite() || range == 0.0 {continue;}
             for i in 1..front.len() - 1 {
-                front[i].crowding_distance +=
-                    (front[i + 1].fitness[obj_idx] - front[i - 1].fitness[obj_idx]) / range;
+                let difference = front[i + 1].scores[obj_idx] - front[i - 1].scores[obj_idx];
+                if difference.is_finite() {
+                    front[i].crowding_distance += difference / range;
+                }
             }
         }
     }
 
     fn single_objective_selection(&mut self) {
         let mut combined_pool = self.pool.clone();
-        combined_pool.sort_by(|a, b| a.fitness[0].partial_cmp(&b.fitness[0]).unwrap());
+        combined_pool.sort_by(|a, b| self.scalarize(a).total_cmp(&self.scalarize(b)));
+        for (rank, vector) in combined_pool.iter_mut().enumerate() {
+            vector.rank = rank;
+            vector.crowding_distance = 0.0;
+        }
         self.pool = combined_pool.into_iter().take(self.pool_size).collect();
     }
 
@@ -407,25 +1232,29 @@ impl<'a> Environment<'a> {
         combined_pool.extend_from_slice(old_pool);
         let mut fronts = self.non_dominated_sort(&combined_pool);
 
+        // every front is ordered by crowding distance so the new pool goes from best to worst
         let mut new_pool = Vec::new();
-        for front in &mut fronts {
+        for (rank, front) in fronts.iter_mut().enumerate() {
             self.calculate_crowding_distance(front);
+            for vector in front.iter_mut() {
+                vector.rank = rank;
+            }
 
+            let mut sorted_front = front.clone();
+            sorted_front.sort_by(|a, b| b.crowding_distance.total_cmp(&a.crowding_distance));
             if new_pool.len() + front.len() > self.pool_size {
-                let mut sorted_front = front.clone();
-                sorted_front.sort_by(|a, b| b.crowding_distance.partial_cmp(&a.crowding_distance).unwrap());
                 let remaining_slots = self.pool_size - new_pool.len();
                 new_pool.extend(sorted_front.into_iter().take(remaining_slots));
                 break;
             } else {
-                new_pool.extend(front.clone());
+                new_pool.extend(sorted_front);
             }
         }
 
         self.pool = new_pool;
     }
 
-    fn non_dominated_sort(&self, vectors: &[Vector]) -> Vec<Vec<Vector>> {
+    pub fn non_dominated_sort(&self, vectors: &[Vector]) -> Vec<Vec<Vector>> {
         let mut fronts: Vec<Vec<Vector>> = Vec::new();
         let mut domination_count: Vec<usize> = vec![0; vectors.len()];
         let mut dominated_solutions: Vec<Vec<usize>> = vec![Vec::new(); vectors.len()];
@@ -470,15 +1299,20 @@ impl<'a> Environment<'a> {
             return vec![0.0; self.num_objectives];
         }
 
+        // non-finite values are left out of the statistics
         let mut sums = vec![0.0; self.num_objectives];
+        let mut counts = vec![0usize; self.num_objectives];
 
         for vector in self.pool.iter() {
             for (i, fitness) in vector.fitness.iter().enumerate() {
-                sums[i] += fitness;
+                if fitness.is_finite() {
+                    sums[i] += fitness;
+                    counts[i] += 1;
+                }
             }
         }
 
-        sums.iter().map(|sum| sum / self.pool_size as f64).collect()
+        sums.iter().zip(counts.iter()).map(|(sum, &count)| sum / count.max(1) as f64).collect()
     }
 
     pub fn fitness_std_dev(&self) -> Vec<f64> {
@@ -488,25 +1322,30 @@ impl<'a> Environment<'a> {
 
         let means = self.fitness_mean();
         let mut sum_of_squares = vec![0.0; self.num_objectives];
+        let mut counts = vec![0usize; self.num_objectives];
         for vector in self.pool.iter() {
             for (i, fitness) in vector.fitness.iter().enumerate() {
-                sum_of_squares[i] += (fitness - means[i]).powi(2);
+                if fitness.is_finite() {
+                    sum_of_squares[i] += (fitness - means[i]).powi(2);
+                    counts[i] += 1;
+                }
             }
         }
 
         sum_of_squares
             .iter()
-            .map(|sum| (sum / self.pool_size as f64).sqrt())
+            .zip(counts.iter())
+            .map(|(sum, &count)| (sum / count.max(1) as f64).sqrt())
             .collect()
     }
 }
 
 // checks if `a` dominates `b`, intially assumes that it does
-fn dominates(a: &Vector, b: &Vector) -> bool {
+pub fn dominates(a: &Vector, b: &Vector) -> bool {
     let mut better_in_all = true;
     let mut strictly_better_in_one = false;
 
-    for (f_a, f_b) in a.fitness.iter().zip(&b.fitness) {
+    for (f_a, f_b) in a.scores.iter().zip(&b.scores) {
         if f_a > f_b {
             better_in_all = false;
         }
@@ -518,3 +1357,170 @@ fn dominates(a: &Vector, b: &Vector) -> bool {
     better_in_all && strictly_better_in_one
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_at_climbs_from_the_initial_to_the_final_scale() {
+        let schedule = ResolutionSchedule {
+            eval_scale: 0.8,
+            coarse_to_fine: true,
+            initial_scale: 0.1,
+            levels: 4,
+            generations_per_level: 25,
+        };
+        assert!((schedule.scale_at(0) - 0.1).abs() < 1e-12);
+        assert!((schedule.scale_at(75) - 0.8).abs() < 1e-12);
+        assert!((schedule.scale_at(10_000) - 0.8).abs() < 1e-12);
+        for generation in 0..200 {
+            assert!(schedule.scale_at(generation) <= schedule.scale_at(generation + 1));
+        }
+
+        let fixed = ResolutionSchedule { coarse_to_fine: false, ..schedule };
+        assert_eq!(fixed.scale_at(0), 0.8);
+        // an initial scale above the final one is clamped down to it
+        let inverted = ResolutionSchedule { initial_scale: 2.0, ..schedule };
+        assert!((0..200).all(|generation| (inverted.scale_at(generation) - 0.8).abs() < 1e-12));
+    }
+
+    #[test]
+    fn alignment_pairs_every_triangle_within_the_other_genome() {
+        assert_eq!(Alignment::Positional.index(3, 10, 5), Some(3));
+        assert_eq!(Alignment::Positional.index(5, 10, 5), None);
+        assert_eq!(Alignment::Proportional.index(0, 10, 0), None);
+        // the indices stay in order and in bounds, and a shorter genome is covered entirely
+        for (len, other_len) in [(1, 1), (10, 5), (5, 10), (7, 3), (250, 499), (499, 250)] {
+            let indices: Vec<usize> = (0..len).map(|i| Alignment::Proportional.index(i, len, other_len).unwrap()).collect();
+            assert!(indices.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!(indices[len - 1] < other_len);
+            if len >= other_len {
+                assert!((0..other_len).all(|j| indices.contains(&j)));
+            }
+        }
+        assert_eq!(Alignment::Proportional.index(2, 5, 10), Some(5));
+        assert!((0..8).all(|i| Alignment::Proportional.index(i, 8, 8) == Some(i)));
+    }
+
+    #[test]
+    fn differential_mutation_clamps_without_wrapping_around() {
+        let params = PaintParams::default();
+        let triangle = |x: u32, y: u32, value: u8| Triangle {
+            vertex1: Vertex { x, y },
+            vertex2: Vertex { x, y },
+            vertex3: Vertex { x, y },
+            color: Rgba([value, value, value, value]),
+            blend: BlendMode::SourceOver,
+        };
+        let (width, height) = (100, 50);
+
+        // b - c below zero would wrap around in unsigned arithmetic
+        let low = Triangle::differential_mutation(&triangle(2, 1, 3), &triangle(0, 0, 0), &triangle(99, 49, 255), 1.7, width, height, &params);
+        assert_eq!((low.vertex1.x, low.vertex1.y), (0, 0));
+        assert_eq!(low.color, Rgba([0, 0, 0, params.min_alpha]));
+
+        let high = Triangle::differential_mutation(&triangle(98, 48, 250), &triangle(99, 49, 255), &triangle(0, 0, 0), 1.7, width, height, &params);
+        assert_eq!((high.vertex3.x, high.vertex3.y), (width - 1, height - 1));
+        assert_eq!(high.color, Rgba([255, 255, 255, params.max_alpha]));
+
+        let inside = Triangle::differential_mutation(&triangle(50, 20, 100), &triangle(30, 10, 60), &triangle(20, 5, 40), 0.5, width, height, &params);
+        assert_eq!((inside.vertex2.x, inside.vertex2.y), (55, 23));
+        assert_eq!(inside.color, Rgba([110, 110, 110, 110]));
+    }
+
+    #[test]
+    fn order_preserving_crossover_inherits_the_mutant_paint_order() {
+        // every triangle is told apart by its first x, the mutant ones from 100 on
+        let layers = |first: u32, len: u32| Vector {
+            triangles: (first..first + len)
+                .map(|x| Triangle {
+                    vertex1: Vertex { x, y: 0 },
+                    vertex2: Vertex { x, y: 1 },
+                    vertex3: Vertex { x: 0, y: 1 },
+                    color: Rgba([0, 0, 0, 100]),
+                    blend: BlendMode::SourceOver,
+                })
+                .collect(),
+            background: [0; 3],
+            fitness: vec![0.0],
+            scores: vec![0.0],
+            rank: 0,
+            crowding_distance: 0.0,
+        };
+        let params = GenomeParams { crossover: LayerCrossover::OrderPreserving, ..GenomeParams::default() };
+        let (mutant, target) = (layers(100, 20), layers(0, 20));
+
+        let mut moved = false;
+        for _ in 0..200 {
+            let trial = Vector::crossover(&mutant, &target, 0.3, &params, 1);
+            assert_eq!(trial.triangles.len(), 20);
+            let sources: Vec<u32> = trial.triangles.iter().map(|triangle| triangle.vertex1.x).collect();
+            let block: Vec<usize> = (0..20).filter(|&i| sources[i] >= 100).collect();
+            let kept: Vec<u32> = sources.iter().copied().filter(|&x| x < 100).collect();
+
+            // the mutant layers are consecutive and in the mutant's order, at their depth there
+            if let Some(&first) = block.first() {
+                assert!(block.iter().enumerate().all(|(k, &i)| i == first + k));
+                assert!(block.iter().all(|&i| sources[i] == 100 + i as u32));
+            }
+            // the target triangles keep their relative order
+            assert!(kept.windows(2).all(|pair| pair[0] < pair[1]));
+            moved |= sources.iter().enumerate().any(|(i, &x)| x < 100 && x != i as u32);
+        }
+        // unlike the positional crossover, target triangles move to other layers
+        assert!(moved);
+    }
+
+    #[test]
+    fn genes_round_trip_through_a_template() {
+        let params = PaintParams::default();
+        for (width, height) in [(2, 3), (640, 480)] {
+            let vector = Vector::generate_random_vector(width, height, 40, &params, 1);
+            let genes = vector.to_genes(width, height);
+            assert_eq!(genes.len(), 40 * 10 + 3);
+            assert!(genes.iter().all(|gene| (0.0..=1.0).contains(gene)));
+
+            // only the length and blend modes are taken from the template
+            let template = Vector::generate_random_vector(width, height, 40, &params, 1);
+            let decoded = Vector::from_genes(&genes, &template, width, height, &params);
+            assert_eq!(decoded.to_genes(width, height), genes);
+            assert_eq!(decoded.background, vector.background);
+        }
+    }
+
+    #[test]
+    fn non_finite_fitness_is_scored_as_the_worst() {
+        // the first vector gets NaN, the second an infinity and the others their index
+        let measure: FitnessFunction = Arc::new(|env: &mut Environment, idx: usize| {
+            for (i, vector) in env.pool.iter_mut().enumerate() {
+                vector.fitness[idx] = match i {
+                    0 => f64::NAN,
+                    1 => f64::INFINITY,
+                    _ => i as f64,
+                };
+            }
+        });
+        let objectives = vec![
+            Objective::new("minimised", measure.clone(), Direction::Minimize),
+            Objective::new("maximised", measure, Direction::Maximize),
+        ];
+        for scalarization in [Scalarization::None, Scalarization::WeightedSum, Scalarization::Tchebycheff] {
+            let mut env = Environment::new(4, 0.5, 0.5, RgbaImage::new(8, 8), 5, 1, objectives.clone(), 3).unwrap();
+            env.normalization = Normalization::MinMax;
+            env.scalarization = scalarization;
+            env.pool = (0..4).map(|_| Vector::generate_random_vector(8, 8, 5, &env.paint_params, 2)).collect();
+            env.calculate_fitness_for_population();
+
+            for vector in &env.pool[..2] {
+                assert_eq!(vector.scores, vec![f64::INFINITY; 2]);
+                assert_eq!(env.scalarize(vector), f64::INFINITY);
+            }
+            for vector in &env.pool[2..] {
+                assert!(vector.scores.iter().all(|score| score.is_finite()));
+                assert!(env.scalarize(vector).is_finite());
+            }
+            assert_eq!(env.non_finite_counts, vec![2, 2]);
+        }
+    }
+}